@@ -0,0 +1,125 @@
+use std::mem::size_of;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use crate::clock;
+use crate::stats::CacheStats;
+use crate::types::{CacheEntry, EntryMap};
+
+// Often enough to alert in time; rare enough that walking the map for a memory
+// estimate stays cheap and the eviction rate is measured over a useful window
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Limits checked about once a second; unset limits are not checked
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlarmThresholds {
+    pub(crate) max_entries: Option<usize>,
+    pub(crate) max_memory_bytes: Option<usize>,
+    pub(crate) max_evictions_per_sec: Option<f64>,
+}
+
+impl AlarmThresholds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = Some(entries);
+        self
+    }
+
+    // Compared against an estimate: key and value bytes plus fixed per-entry
+    // overhead. Measuring it walks the whole map on every check.
+    pub fn with_max_memory_bytes(mut self, bytes: usize) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_evictions_per_sec(mut self, rate: f64) -> Self {
+        self.max_evictions_per_sec = Some(rate);
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.max_entries.is_none() && self.max_memory_bytes.is_none() && self.max_evictions_per_sec.is_none()
+    }
+}
+
+// Raised once when a limit is crossed, and again only after the measurement
+// has dropped back under the limit in between
+#[derive(Debug, Clone, PartialEq)]
+pub enum Alarm {
+    EntryCount { entries: usize, threshold: usize },
+    MemoryUsage { bytes: usize, threshold: usize },
+    EvictionRate { per_sec: f64, threshold: f64 },
+}
+
+#[derive(Debug)]
+pub(crate) struct AlarmMonitor {
+    subscribers: Vec<Sender<Alarm>>,
+    raised: [bool; 3],
+    last_check: Instant,
+    last_evictions: u64,
+}
+
+impl AlarmMonitor {
+    pub fn new() -> Self {
+        AlarmMonitor { subscribers: Vec::new(), raised: [false; 3], last_check: clock::now(), last_evictions: 0 }
+    }
+
+    pub fn subscribe(&mut self, subscriber: Sender<Alarm>) {
+        self.subscribers.push(subscriber);
+    }
+
+    // When the event loop should wake up for the next check
+    pub fn next_check(&self) -> Instant {
+        self.last_check + CHECK_INTERVAL
+    }
+
+    pub fn check(&mut self, thresholds: &AlarmThresholds, data: &EntryMap, stats: &CacheStats, now: Instant) {
+        if now.duration_since(self.last_check) < CHECK_INTERVAL {
+            return;
+        }
+        let elapsed = now.duration_since(self.last_check).as_secs_f64();
+        let evicted = stats.evictions - self.last_evictions;
+        self.last_check = now;
+        self.last_evictions = stats.evictions;
+        if thresholds.is_empty() {
+            return;
+        }
+
+        let entry_alarm = thresholds.max_entries.map(|threshold| {
+            (data.len() > threshold, Alarm::EntryCount { entries: data.len(), threshold })
+        });
+        let memory_alarm = thresholds.max_memory_bytes.map(|threshold| {
+            let bytes = estimated_memory(data);
+            (bytes > threshold, Alarm::MemoryUsage { bytes, threshold })
+        });
+        let eviction_alarm = thresholds.max_evictions_per_sec.map(|threshold| {
+            let per_sec = evicted as f64 / elapsed;
+            (per_sec > threshold, Alarm::EvictionRate { per_sec, threshold })
+        });
+
+        for (slot, checked) in [entry_alarm, memory_alarm, eviction_alarm].into_iter().enumerate() {
+            let Some((exceeded, alarm)) = checked else {
+                continue;
+            };
+            if exceeded && !self.raised[slot] {
+                self.raise(alarm);
+            }
+            self.raised[slot] = exceeded;
+        }
+    }
+
+    fn raise(&mut self, alarm: Alarm) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(alarm = ?alarm, "cache alarm");
+        self.subscribers.retain(|subscriber| subscriber.send(alarm.clone()).is_ok());
+    }
+}
+
+fn estimated_memory(data: &EntryMap) -> usize {
+    data.iter()
+        .map(|(key, entry)| key.len() + entry.value.len() + size_of::<String>() + size_of::<CacheEntry>())
+        .sum()
+}
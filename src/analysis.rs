@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::types::EntryMap;
+
+const DISTRIBUTION_BUCKETS: usize = 64;
+
+// Power-of-two buckets over plain numbers: bucket `i` counts samples below
+// `2^i`. The unit depends on what was measured (bytes or milliseconds).
+#[derive(Debug, Clone)]
+pub struct Distribution {
+    buckets: [u64; DISTRIBUTION_BUCKETS],
+    count: u64,
+    max: u64,
+}
+
+impl Default for Distribution {
+    fn default() -> Self {
+        Distribution { buckets: [0; DISTRIBUTION_BUCKETS], count: 0, max: 0 }
+    }
+}
+
+impl Distribution {
+    fn record(&mut self, value: u64) {
+        let bucket = ((u64::BITS - value.leading_zeros()) as usize).min(DISTRIBUTION_BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    // Non-empty buckets as (exclusive upper bound, samples), smallest first
+    pub fn buckets(&self) -> Vec<(u64, u64)> {
+        self.buckets.iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bucket, count)| (1u64.checked_shl(bucket as u32).unwrap_or(u64::MAX), *count))
+            .collect()
+    }
+
+    // Upper bound of the bucket containing the requested percentile (0-100)
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+
+        let target = ((percentile.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return (1u64 << bucket).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixUsage {
+    pub(crate) prefix: String,
+    pub(crate) entries: usize,
+    pub(crate) bytes: usize,
+}
+
+impl PrefixUsage {
+    // Everything up to and including the first ':', or "" for keys without one
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    // Key plus value bytes
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+// One-shot picture of the keyspace for tuning max_size, TTLs and eviction
+#[derive(Debug, Clone, Default)]
+pub struct KeyspaceReport {
+    pub(crate) entries: usize,
+    pub(crate) value_sizes: Distribution,
+    pub(crate) ttls: Distribution,
+    pub(crate) idle_times: Distribution,
+    pub(crate) never_accessed: usize,
+    pub(crate) prefixes: Vec<PrefixUsage>,
+}
+
+impl KeyspaceReport {
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    // In bytes
+    pub fn value_sizes(&self) -> &Distribution {
+        &self.value_sizes
+    }
+
+    // Remaining TTL in milliseconds, over entries that have one
+    pub fn ttls(&self) -> &Distribution {
+        &self.ttls
+    }
+
+    pub fn entries_without_ttl(&self) -> usize {
+        self.entries - self.ttls.count as usize
+    }
+
+    // Milliseconds since each entry was last read or written
+    pub fn idle_times(&self) -> &Distribution {
+        &self.idle_times
+    }
+
+    pub fn never_accessed(&self) -> usize {
+        self.never_accessed
+    }
+
+    pub fn never_accessed_percent(&self) -> f64 {
+        if self.entries == 0 {
+            0.0
+        } else {
+            self.never_accessed as f64 / self.entries as f64 * 100.0
+        }
+    }
+
+    pub fn top_prefixes_by_count(&self, n: usize) -> Vec<PrefixUsage> {
+        let mut prefixes = self.prefixes.clone();
+        prefixes.sort_by(|a, b| b.entries.cmp(&a.entries).then_with(|| a.prefix.cmp(&b.prefix)));
+        prefixes.truncate(n);
+        prefixes
+    }
+
+    pub fn top_prefixes_by_bytes(&self, n: usize) -> Vec<PrefixUsage> {
+        let mut prefixes = self.prefixes.clone();
+        prefixes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.prefix.cmp(&b.prefix)));
+        prefixes.truncate(n);
+        prefixes
+    }
+}
+
+pub(crate) fn analyze(entries: &EntryMap, now: Instant) -> KeyspaceReport {
+    let mut report = KeyspaceReport::default();
+    let mut prefixes: HashMap<&str, PrefixUsage> = HashMap::new();
+
+    for (key, entry) in entries.iter() {
+        let remaining = match entry.expiration {
+            Some(exp) if exp <= now => continue,
+            Some(exp) => Some(exp.duration_since(now)),
+            None => None,
+        };
+
+        report.entries += 1;
+        report.value_sizes.record(entry.value.len() as u64);
+        if let Some(remaining) = remaining {
+            report.ttls.record(remaining.as_millis() as u64);
+        }
+        report.idle_times.record(now.saturating_duration_since(entry.last_accessed).as_millis() as u64);
+        if entry.access_count == 0 {
+            report.never_accessed += 1;
+        }
+
+        let prefix = key.find(':').map_or("", |end| &key[..=end]);
+        let usage = prefixes.entry(prefix).or_insert_with(|| PrefixUsage { prefix: prefix.to_string(), entries: 0, bytes: 0 });
+        usage.entries += 1;
+        usage.bytes += key.len() + entry.value.len();
+    }
+
+    report.prefixes = prefixes.into_values().collect();
+    report
+}
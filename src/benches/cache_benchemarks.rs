@@ -1,6 +1,6 @@
-use criterion:: Criterion;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use std::time::Duration;
-use advanced_cache::{Cache, CacheConfig};
+use mini_redis::{Cache, CacheConfig, ResponseSlot};
 
 
 fn cache_benchmark(c: &mut Criterion) {
@@ -24,6 +24,13 @@ fn cache_benchmark(c: &mut Criterion) {
         })
     });
 
+    c.bench_function("get operation with reused slot", |b| {
+        let slot = ResponseSlot::new();
+        b.iter(|| {
+            black_box(cache.get_with_slot(black_box("bench_key"), &slot).unwrap())
+        })
+    });
+
     c.bench_function("bulk set 100 items", |b| {
         let items: Vec<(String, String)> = (0..100)
             .map(|i| (format!("key{}", i), format!("value{}", i)))
@@ -41,6 +48,19 @@ fn cache_benchmark(c: &mut Criterion) {
             black_box(cache.bulk_get(black_box(keys.clone())).unwrap())
         })
     });
+
+    c.bench_function("bulk get 1000 items", |b| {
+        let items: Vec<(String, String)> = (0..1000)
+            .map(|i| (format!("multi{}", i), format!("value{}", i)))
+            .collect();
+        cache.bulk_set(items).unwrap();
+        let keys: Vec<String> = (0..1000)
+            .map(|i| format!("multi{}", i))
+            .collect();
+        b.iter(|| {
+            black_box(cache.bulk_get(black_box(keys.clone())).unwrap())
+        })
+    });
 }
 
 criterion_group! {
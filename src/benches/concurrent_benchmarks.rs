@@ -0,0 +1,174 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::env;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use mini_redis::{Cache, CacheConfig};
+
+// Each setting can be overridden from the environment, lists comma-separated:
+//   MINI_REDIS_BENCH_THREADS=1,4,8  MINI_REDIS_BENCH_READ_RATIOS=0.5,0.9
+//   MINI_REDIS_BENCH_VALUE_SIZES=5  MINI_REDIS_BENCH_KEYSPACE=10000
+//   MINI_REDIS_BENCH_ZIPF=0.99
+struct Workload {
+    thread_counts: Vec<usize>,
+    read_ratios: Vec<f64>,
+    value_sizes: Vec<usize>,
+    keyspace: usize,
+    zipf_exponent: f64,
+}
+
+impl Workload {
+    fn from_env() -> Self {
+        let workload = Workload {
+            thread_counts: env_list("MINI_REDIS_BENCH_THREADS", &[1, 4, 8]),
+            read_ratios: env_list("MINI_REDIS_BENCH_READ_RATIOS", &[0.5, 0.9]),
+            value_sizes: env_list("MINI_REDIS_BENCH_VALUE_SIZES", &[5]),
+            keyspace: env_list("MINI_REDIS_BENCH_KEYSPACE", &[10_000])[0],
+            zipf_exponent: env_list("MINI_REDIS_BENCH_ZIPF", &[0.99])[0],
+        };
+        assert!(workload.thread_counts.iter().all(|&threads| threads > 0), "thread counts must be positive");
+        assert!(workload.read_ratios.iter().all(|ratio| (0.0..=1.0).contains(ratio)), "read ratios must be between 0 and 1");
+        assert!(workload.keyspace > 0, "the keyspace must not be empty");
+        workload
+    }
+}
+
+// Panics on a malformed value rather than quietly benchmarking the defaults
+fn env_list<T: FromStr + Clone>(name: &str, default: &[T]) -> Vec<T> {
+    match env::var(name) {
+        Ok(value) => {
+            let list: Vec<T> = value.split(',')
+                .map(|item| item.trim().parse().unwrap_or_else(|_| panic!("{}: cannot parse {:?}", name, item)))
+                .collect();
+            assert!(!list.is_empty(), "{} is empty", name);
+            list
+        },
+        Err(_) => default.to_vec(),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum KeyDistribution {
+    Uniform,
+    Zipfian,
+}
+
+impl KeyDistribution {
+    fn name(&self) -> &'static str {
+        match self {
+            KeyDistribution::Uniform => "uniform",
+            KeyDistribution::Zipfian => "zipfian",
+        }
+    }
+}
+
+// xorshift64*, good enough for picking keys and operations
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct KeySampler {
+    distribution: KeyDistribution,
+    keyspace: usize,
+    zipf_cdf: Vec<f64>,
+}
+
+impl KeySampler {
+    fn new(distribution: KeyDistribution, keyspace: usize, zipf_exponent: f64) -> Self {
+        let mut zipf_cdf = Vec::with_capacity(keyspace);
+        if let KeyDistribution::Zipfian = distribution {
+            let weights: Vec<f64> = (1..=keyspace).map(|rank| 1.0 / (rank as f64).powf(zipf_exponent)).collect();
+            let total: f64 = weights.iter().sum();
+            let mut acc = 0.0;
+            for weight in weights {
+                acc += weight / total;
+                zipf_cdf.push(acc);
+            }
+        }
+        KeySampler { distribution, keyspace, zipf_cdf }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> usize {
+        match self.distribution {
+            KeyDistribution::Uniform => (rng.next_u64() % self.keyspace as u64) as usize,
+            KeyDistribution::Zipfian => {
+                let point = rng.next_f64();
+                self.zipf_cdf.partition_point(|&p| p < point).min(self.keyspace - 1)
+            }
+        }
+    }
+}
+
+// Issues exactly `iters` operations split across the threads, so criterion's
+// per-iteration time is per operation even when iters < threads
+fn run_workload(cache: &Cache, sampler: &KeySampler, threads: usize, read_ratio: f64, value: &str, iters: u64) -> Duration {
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for seed in 0..threads {
+            let ops = iters / threads as u64 + u64::from((seed as u64) < iters % threads as u64);
+            if ops == 0 {
+                continue;
+            }
+            scope.spawn(move || {
+                let mut rng = Rng(0x9E37_79B9_7F4A_7C15 ^ (seed as u64 + 1));
+                for _ in 0..ops {
+                    let key = format!("key{}", sampler.sample(&mut rng));
+                    if rng.next_f64() < read_ratio {
+                        black_box(cache.get(&key).unwrap());
+                    } else {
+                        cache.set(key, value.to_string(), None).unwrap();
+                    }
+                }
+            });
+        }
+    });
+
+    start.elapsed()
+}
+
+fn concurrent_benchmark(c: &mut Criterion) {
+    let workload = Workload::from_env();
+
+    for &value_size in &workload.value_sizes {
+        let value = "v".repeat(value_size);
+        let cache = Cache::new(CacheConfig::default());
+        cache.bulk_set((0..workload.keyspace).map(|i| (format!("key{}", i), value.clone()))).unwrap();
+
+        for distribution in [KeyDistribution::Uniform, KeyDistribution::Zipfian] {
+            let sampler = KeySampler::new(distribution, workload.keyspace, workload.zipf_exponent);
+            let mut group = c.benchmark_group(format!("mixed {} {}B", distribution.name(), value_size));
+            // One operation per iteration, so this reports operations per second
+            group.throughput(Throughput::Elements(1));
+
+            for &read_ratio in &workload.read_ratios {
+                for &threads in &workload.thread_counts {
+                    let id = BenchmarkId::new(format!("{}% reads", (read_ratio * 100.0).round() as u32), threads);
+                    group.bench_with_input(id, &threads, |b, &threads| {
+                        b.iter_custom(|iters| run_workload(&cache, &sampler, threads, read_ratio, &value, iters))
+                    });
+                }
+            }
+
+            group.finish();
+        }
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(10));
+    targets = concurrent_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::time::Duration;
+use mini_redis::{Cache, CacheConfig, HashAlgorithm};
+use rustc_hash::FxBuildHasher;
+
+const KEY_COUNT: usize = 10_000;
+
+fn small_keys() -> Vec<String> {
+    (0..KEY_COUNT).map(|i| format!("u:{}", i)).collect()
+}
+
+fn map_lookups<S: BuildHasher>(c: &mut Criterion, name: &str, hasher: S) {
+    let keys = small_keys();
+    let mut map = HashMap::with_capacity_and_hasher(KEY_COUNT, hasher);
+    for key in &keys {
+        map.insert(key.clone(), "value".to_string());
+    }
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(map.get(black_box(key.as_str())));
+            }
+        })
+    });
+}
+
+fn cache_lookups(c: &mut Criterion, name: &str, algorithm: HashAlgorithm) {
+    let keys = small_keys();
+    let cache = Cache::new(CacheConfig::default().with_hash_algorithm(algorithm));
+    cache.bulk_set(keys.iter().map(|key| (key.clone(), "value".to_string()))).unwrap();
+
+    c.bench_function(name, |b| {
+        b.iter(|| black_box(cache.bulk_get(black_box(keys.clone())).unwrap()))
+    });
+}
+
+fn hasher_benchmark(c: &mut Criterion) {
+    map_lookups(c, "10k small-key lookups, fx hasher", FxBuildHasher);
+    map_lookups(c, "10k small-key lookups, siphash", RandomState::new());
+    cache_lookups(c, "cache bulk_get 10k small keys, fast hashing", HashAlgorithm::Fast);
+    cache_lookups(c, "cache bulk_get 10k small keys, DoS-resistant hashing", HashAlgorithm::DosResistant);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(10));
+    targets = hasher_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,230 @@
+use std::env;
+use std::num::NonZeroUsize;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use mini_redis::{Cache, CacheConfig};
+
+const USAGE: &str = "usage: mini-redis-bench [--clients <n>] [--requests <n>] [--pipeline <depth>] [--get-ratio <0-1>]
+                        [--keyspace <keys>] [--zipf <exponent>] [--value-size <bytes>] [--max-size <entries>]
+
+Each client is a thread issuing its share of the requests against one shared cache.
+With --pipeline above 1, requests are sent in batches of that depth, the gets as one
+bulk_get and the sets as one bulk_set; latencies are then per batch.
+Keys are drawn uniformly unless --zipf gives an exponent (0.99 is typical).";
+
+struct Options {
+    clients: usize,
+    requests: usize,
+    pipeline: usize,
+    get_ratio: f64,
+    keyspace: usize,
+    zipf: Option<f64>,
+    value_size: usize,
+    max_size: Option<NonZeroUsize>,
+}
+
+// xorshift64*, good enough for picking keys and operations
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct KeySampler {
+    keyspace: usize,
+    zipf_cdf: Vec<f64>,
+}
+
+impl KeySampler {
+    fn new(keyspace: usize, zipf: Option<f64>) -> Self {
+        let mut zipf_cdf = Vec::new();
+        if let Some(exponent) = zipf {
+            let weights: Vec<f64> = (1..=keyspace).map(|rank| 1.0 / (rank as f64).powf(exponent)).collect();
+            let total: f64 = weights.iter().sum();
+            let mut acc = 0.0;
+            for weight in weights {
+                acc += weight / total;
+                zipf_cdf.push(acc);
+            }
+        }
+        KeySampler { keyspace, zipf_cdf }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> usize {
+        if self.zipf_cdf.is_empty() {
+            return (rng.next_u64() % self.keyspace as u64) as usize;
+        }
+        let point = rng.next_f64();
+        self.zipf_cdf.partition_point(|&p| p < point).min(self.keyspace - 1)
+    }
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|msg| {
+        eprintln!("{}\n\n{}", msg, USAGE);
+        process::exit(2);
+    });
+
+    let mut config = CacheConfig::default();
+    if let Some(max_size) = options.max_size {
+        config = config.with_max_size(max_size);
+    }
+    let cache = Arc::new(Cache::new(config));
+    let sampler = Arc::new(KeySampler::new(options.keyspace, options.zipf));
+    let value = "x".repeat(options.value_size);
+    let per_client = options.requests / options.clients;
+
+    let started = Instant::now();
+    let clients: Vec<_> = (0..options.clients)
+        .map(|client| {
+            let cache = Arc::clone(&cache);
+            let sampler = Arc::clone(&sampler);
+            let value = value.clone();
+            let pipeline = options.pipeline;
+            let get_ratio = options.get_ratio;
+            thread::spawn(move || {
+                let mut rng = Rng(0x9E37_79B9_7F4A_7C15 ^ (client as u64 + 1));
+                let mut latencies = Vec::with_capacity(per_client / pipeline + 1);
+                let mut remaining = per_client;
+                while remaining > 0 {
+                    let depth = pipeline.min(remaining);
+                    remaining -= depth;
+                    let mut gets = Vec::new();
+                    let mut sets = Vec::new();
+                    for _ in 0..depth {
+                        let key = format!("key{}", sampler.sample(&mut rng));
+                        if rng.next_f64() < get_ratio {
+                            gets.push(key);
+                        } else {
+                            sets.push((key, value.clone()));
+                        }
+                    }
+
+                    let op_started = Instant::now();
+                    let result = if depth == 1 {
+                        match (gets.pop(), sets.pop()) {
+                            (Some(key), _) => cache.get(&key).map(|_| ()),
+                            (_, Some((key, value))) => cache.set(key, value, None),
+                            (None, None) => Ok(()),
+                        }
+                    } else {
+                        let got = if gets.is_empty() { Ok(()) } else { cache.bulk_get(gets.iter().map(String::as_str)).map(|_| ()) };
+                        got.and_then(|_| if sets.is_empty() { Ok(()) } else { cache.bulk_set(sets) })
+                    };
+                    latencies.push(op_started.elapsed());
+
+                    if let Err(e) = result {
+                        eprintln!("client {}: cache error: {}", client, e);
+                        process::exit(1);
+                    }
+                }
+                latencies
+            })
+        })
+        .collect();
+
+    let mut latencies: Vec<Duration> = clients.into_iter()
+        .flat_map(|client| client.join().unwrap_or_else(|_| {
+            eprintln!("client thread panicked");
+            process::exit(1);
+        }))
+        .collect();
+    let elapsed = started.elapsed();
+    let stats = cache.get_stats().unwrap_or_else(|e| {
+        eprintln!("failed to read stats: {}", e);
+        process::exit(1);
+    });
+    latencies.sort();
+
+    let requests = per_client * options.clients;
+    println!("Clients: {}", options.clients);
+    println!("Pipeline: {}", options.pipeline);
+    println!("Requests: {}", requests);
+    println!("Elapsed: {:.2?}", elapsed);
+    println!("Throughput: {:.0} requests/sec", requests as f64 / elapsed.as_secs_f64());
+    println!("Hit Rate: {:.2}%", stats.hit_rate());
+    println!("Evictions: {}", stats.evictions());
+    let unit = if options.pipeline > 1 { "batch" } else { "request" };
+    for percentile in [50.0, 90.0, 99.0, 99.9] {
+        println!("p{} per {}: {:.2?}", percentile, unit, percentile_of(&latencies, percentile));
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+    let mut options = Options {
+        clients: 4,
+        requests: 100_000,
+        pipeline: 1,
+        get_ratio: 0.9,
+        keyspace: 10_000,
+        zipf: None,
+        value_size: 32,
+        max_size: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--clients" => options.clients = next_value(&mut args, "--clients")?,
+            "--requests" => options.requests = next_value(&mut args, "--requests")?,
+            "--pipeline" => options.pipeline = next_value(&mut args, "--pipeline")?,
+            "--get-ratio" => {
+                options.get_ratio = next_value(&mut args, "--get-ratio")?;
+                if !(0.0..=1.0).contains(&options.get_ratio) {
+                    return Err("--get-ratio must be between 0 and 1".to_string());
+                }
+            },
+            "--keyspace" => options.keyspace = next_value(&mut args, "--keyspace")?,
+            "--zipf" => {
+                let exponent: f64 = next_value(&mut args, "--zipf")?;
+                if exponent <= 0.0 {
+                    return Err("--zipf must be positive".to_string());
+                }
+                options.zipf = Some(exponent);
+            },
+            "--value-size" => options.value_size = next_value(&mut args, "--value-size")?,
+            "--max-size" => {
+                let size: usize = next_value(&mut args, "--max-size")?;
+                options.max_size = Some(NonZeroUsize::new(size).ok_or("--max-size must be positive")?);
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+
+    if options.clients == 0 || options.pipeline == 0 || options.keyspace == 0 {
+        return Err("--clients, --pipeline and --keyspace must be positive".to_string());
+    }
+    if options.requests < options.clients {
+        return Err("--requests must be at least --clients".to_string());
+    }
+    Ok(options)
+}
+
+fn next_value<T: std::str::FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String> {
+    args.next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} expects a numeric value", flag))
+}
+
+fn percentile_of(sorted: &[Duration], percentile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,189 @@
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::num::NonZeroUsize;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+use mini_redis::{Cache, CacheConfig};
+
+const USAGE: &str = "usage: mini-redis-replay <trace-file> [--speed <factor>] [--max-size <entries>] [--ttl-secs <seconds>]
+
+Trace lines are `<timestamp_ms>,<op>,<key>` where op is `get` or `set`.
+Gets that miss are filled with a placeholder value, like a cache-aside client would.
+A speed of 0 replays as fast as possible; 1 replays at the recorded pace.
+Other speeds must lie between 0.001 and 1000000.";
+
+const MIN_SPEED: f64 = 0.001;
+const MAX_SPEED: f64 = 1_000_000.0;
+
+struct Options {
+    trace_path: String,
+    speed: f64,
+    max_size: Option<NonZeroUsize>,
+    ttl: Option<Duration>,
+}
+
+enum Op {
+    Get,
+    Set,
+}
+
+struct TraceRecord {
+    timestamp: Duration,
+    op: Op,
+    key: String,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|msg| {
+        eprintln!("{}\n\n{}", msg, USAGE);
+        process::exit(2);
+    });
+
+    let file = File::open(&options.trace_path).unwrap_or_else(|e| {
+        eprintln!("failed to open {}: {}", options.trace_path, e);
+        process::exit(1);
+    });
+
+    let mut config = CacheConfig::default();
+    if let Some(max_size) = options.max_size {
+        config = config.with_max_size(max_size);
+    }
+    if let Some(ttl) = options.ttl {
+        config = config.with_default_ttl(ttl);
+    }
+    let cache = Cache::new(config);
+
+    let mut latencies = Vec::new();
+    let started = Instant::now();
+    let mut first_timestamp = None;
+
+    for (line_no, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("failed to read trace: {}", e);
+            process::exit(1);
+        });
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let record = parse_record(&line).unwrap_or_else(|msg| {
+            eprintln!("line {}: {}", line_no + 1, msg);
+            process::exit(1);
+        });
+
+        if options.speed > 0.0 {
+            let offset = record.timestamp.saturating_sub(*first_timestamp.get_or_insert(record.timestamp));
+            let due = Duration::try_from_secs_f64(offset.as_secs_f64() / options.speed).unwrap_or_else(|e| {
+                eprintln!("line {}: cannot schedule at speed {}: {}", line_no + 1, options.speed, e);
+                process::exit(1);
+            });
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+
+        let op_started = Instant::now();
+        let result = match record.op {
+            Op::Get => match cache.get(&record.key) {
+                Ok(None) => cache.set(record.key, "replayed".to_string(), None),
+                other => other.map(|_| ()),
+            },
+            Op::Set => cache.set(record.key, "replayed".to_string(), None),
+        };
+        latencies.push(op_started.elapsed());
+
+        if let Err(e) = result {
+            eprintln!("line {}: cache error: {}", line_no + 1, e);
+            process::exit(1);
+        }
+    }
+
+    let elapsed = started.elapsed();
+    let stats = cache.get_stats().unwrap_or_else(|e| {
+        eprintln!("failed to read stats: {}", e);
+        process::exit(1);
+    });
+    latencies.sort();
+
+    println!("Operations: {}", latencies.len());
+    println!("Elapsed: {:.2?}", elapsed);
+    println!("Hits: {}", stats.hits());
+    println!("Misses: {}", stats.misses());
+    println!("Evictions: {}", stats.evictions());
+    println!("Hit Rate: {:.2}%", stats.hit_rate());
+    for percentile in [50.0, 90.0, 99.0, 99.9] {
+        println!("p{}: {:.2?}", percentile, percentile_of(&latencies, percentile));
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+    let mut options = Options {
+        trace_path: String::new(),
+        speed: 0.0,
+        max_size: None,
+        ttl: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => {
+                options.speed = next_value(&mut args, "--speed")?;
+                // Also keeps the scaled offsets within what a Duration can hold
+                if !(options.speed == 0.0 || (MIN_SPEED..=MAX_SPEED).contains(&options.speed)) {
+                    return Err(format!("--speed must be 0 or between {} and {}", MIN_SPEED, MAX_SPEED));
+                }
+            },
+            "--max-size" => {
+                let size: usize = next_value(&mut args, "--max-size")?;
+                options.max_size = Some(NonZeroUsize::new(size).ok_or("--max-size must be positive")?);
+            },
+            "--ttl-secs" => {
+                options.ttl = Some(Duration::from_secs(next_value(&mut args, "--ttl-secs")?));
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            path if options.trace_path.is_empty() => options.trace_path = path.to_string(),
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+
+    if options.trace_path.is_empty() {
+        return Err("missing trace file".to_string());
+    }
+    Ok(options)
+}
+
+fn next_value<T: std::str::FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String> {
+    args.next()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} expects a numeric value", flag))
+}
+
+fn parse_record(line: &str) -> Result<TraceRecord, String> {
+    let mut fields = line.splitn(3, ',');
+    let timestamp = fields.next()
+        .and_then(|ts| ts.trim().parse().ok())
+        .map(Duration::from_millis)
+        .ok_or("invalid timestamp")?;
+    let op = match fields.next().map(str::trim) {
+        Some("get") => Op::Get,
+        Some("set") => Op::Set,
+        _ => return Err("op must be get or set".to_string()),
+    };
+    let key = fields.next().ok_or("missing key")?.to_string();
+
+    Ok(TraceRecord { timestamp, op, key })
+}
+
+fn percentile_of(sorted: &[Duration], percentile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    // Calls go through
+    Closed,
+    // Calls should fail fast without reaching the service
+    Open,
+    // The caller that got this may send one trial call and report its outcome
+    HalfOpen,
+}
+
+// Workers sharing a breaker should pass the same policy, since every call
+// evaluates the breaker against the policy it is given
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerPolicy {
+    pub(crate) failure_threshold: usize,
+    pub(crate) window: Duration,
+    pub(crate) open_for: Duration,
+}
+
+impl BreakerPolicy {
+    // Opens after `failure_threshold` failures within the rolling `window`
+    pub fn new(failure_threshold: usize, window: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            window,
+            open_for: Duration::from_secs(30),
+        }
+    }
+
+    // How long the breaker stays open before letting a trial call through
+    pub fn with_open_for(mut self, open_for: Duration) -> Self {
+        self.open_for = open_for;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BreakerEvent {
+    Check,
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Breaker {
+    failures: VecDeque<Instant>,
+    opened_at: Option<Instant>,
+    probe_started: Option<Instant>,
+    window: Duration,
+}
+
+impl Breaker {
+    pub fn apply(&mut self, event: BreakerEvent, policy: &BreakerPolicy, now: Instant) -> BreakerState {
+        self.window = policy.window;
+        while self.failures.front().is_some_and(|at| now.duration_since(*at) > policy.window) {
+            self.failures.pop_front();
+        }
+
+        match event {
+            BreakerEvent::Success if self.opened_at.is_some() => {
+                // Only a trial call's success closes an open breaker
+                if self.probe_started.is_some() {
+                    *self = Breaker { window: policy.window, ..Breaker::default() };
+                }
+            },
+            BreakerEvent::Success => {},
+            BreakerEvent::Failure => {
+                if self.probe_started.take().is_some() {
+                    self.opened_at = Some(now);
+                } else {
+                    self.failures.push_back(now);
+                    if self.failures.len() > policy.failure_threshold {
+                        self.failures.pop_front();
+                    }
+                    if self.opened_at.is_none() && self.failures.len() >= policy.failure_threshold {
+                        self.opened_at = Some(now);
+                    }
+                }
+            },
+            BreakerEvent::Check => {
+                if let Some(opened_at) = self.opened_at {
+                    // Let one caller probe once the breaker has cooled down; if
+                    // that probe never reports back, another gets a turn later
+                    let cooled_down = now.duration_since(opened_at) >= policy.open_for;
+                    let probe_lapsed = self.probe_started.is_none_or(|at| now.duration_since(at) >= policy.open_for);
+                    if cooled_down && probe_lapsed {
+                        self.probe_started = Some(now);
+                        return BreakerState::HalfOpen;
+                    }
+                }
+            },
+        }
+
+        if self.opened_at.is_some() { BreakerState::Open } else { BreakerState::Closed }
+    }
+
+    // Closed with no failures left in the window: nothing worth keeping
+    pub fn is_idle(&self, now: Instant) -> bool {
+        self.opened_at.is_none() && self.failures.back().is_none_or(|at| now.duration_since(*at) > self.window)
+    }
+}
@@ -1,294 +1,2500 @@
-use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
 use std::time::{Duration, Instant};
-use std::sync::Arc;
-use std::thread::{self, JoinHandle};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::config::CacheConfig;
+use crate::alarms::{Alarm, AlarmMonitor};
+use crate::analysis::KeyspaceReport;
+use crate::breaker::{Breaker, BreakerEvent, BreakerPolicy, BreakerState};
+use crate::clock;
+use crate::config::{CacheConfig, KeyNormalizer};
+use crate::delta::{self, Delta, DeltaEntry};
+use crate::dependencies::DependencyGraph;
 use crate::error::CacheError;
+use crate::ghost::{GhostCache, SimulatedPolicy};
+use crate::hashing::KeyHashBuilder;
+use crate::leaderboard::Board;
+use crate::lifecycle::{EventLoopHandle, RunningFlag};
+use crate::middleware::CommandInfo;
+use crate::pattern::glob_match;
+#[cfg(feature = "persistence")]
+use crate::persistence;
+use crate::queue::Queue;
+use crate::snapshot::Snapshot;
 use crate::stats::CacheStats;
-use crate::types::{CacheEntry, ExpirationEntry};
+use crate::stream::SnapshotStream;
+use crate::topk::TopK;
+use crate::unique::UniqueCounter;
+use crate::transport::{self, Receiver, RecvTimeoutError, Sender};
+use crate::types::{CacheEntry, CounterBounds, EntryMap, EntryOrder, ExpirationEntry, Expiry, Lookup, ObjectInfo, Overflow, ResponseSlot, ScanOptions, ScanPage, SetOptions, SlotReply};
+use crate::value::{StoredValue, INLINE_CAPACITY};
+use crate::workers::WorkerPool;
+
+const WARM_BATCH_SIZE: usize = 1024;
+// Capacities, relative to max_size, that the sizing curve simulates
+const SIZING_CURVE_GROWTH: [f64; 4] = [1.0, 1.1, 1.25, 1.5];
+// The normal lane gets a turn at least once every this many priority commands
+const PRIORITY_BURST: usize = 16;
+// Reads of values at least this big go to the value workers, when there are any
+const OFFLOAD_MIN_BYTES: usize = 64 * 1024;
+
+type Callback<T> = Box<dyn FnOnce(Result<T, CacheError>) + Send>;
+
+// Where the event loop delivers a result: a per-call channel, a reused
+// `ResponseSlot` (whose sender goes back with the reply) or a completion
+// callback run on the event loop thread, or on a value worker for the large
+// reads those finish.
+enum Responder<T> {
+    Channel(Sender<Result<T, CacheError>>),
+    Slot(Sender<SlotReply<T>>),
+    Callback(Callback<T>),
+}
+
+impl<T> Responder<T> {
+    fn send(self, result: Result<T, CacheError>) {
+        match self {
+            Responder::Channel(sender) => {
+                let _ = sender.send(result);
+            },
+            Responder::Slot(sender) => {
+                let _ = sender.clone().send(SlotReply { result, sender });
+            },
+            Responder::Callback(callback) => callback(result),
+        }
+    }
+
+    fn fail(self, error: CacheError) {
+        self.send(Err(error));
+    }
+}
+
+// Read-path commands carry keys up to INLINE_CAPACITY bytes inline, so a
+// lookup doesn't allocate just to hand the key to the event loop.
+type CommandKey = StoredValue;
 
 enum CacheCommand {
-    Set { key: String, value: String, ttl: Option<Duration>, resp: Sender<Result<(), CacheError>> },
-    Get { key: String, resp: Sender<Result<Option<String>, CacheError>> },
-    BulkSet { items: Vec<(String, String)>, resp: Sender<Result<(), CacheError>> },
-    BulkGet { keys: Vec<String>, resp: Sender<Result<HashMap<String, Option<String>>, CacheError>> },
-    GetStats { resp: Sender<Result<CacheStats, CacheError>> },
-    UpdateTtl { key: String, ttl: Duration, resp: Sender<Result<bool, CacheError>> },
-    CompareAndSwap { key: String, expected: String, new_value: String, resp: Sender<Result<bool, CacheError>> },
+    Set { key: String, value: String, options: SetOptions, resp: Responder<()> },
+    SetWithDependencies { key: String, value: String, ttl: Option<Duration>, dependencies: Vec<String>, resp: Responder<()> },
+    Get { key: CommandKey, resp: Responder<Option<String>> },
+    Delete { key: String, resp: Responder<bool> },
+    TryLease { key: String, ttl: Duration, resp: Responder<bool> },
+    ReleaseLease { key: String, resp: Responder<()> },
+    Breaker { service: String, event: BreakerEvent, policy: BreakerPolicy, resp: Responder<BreakerState> },
+    ScheduleDelete { pattern: String, every: Duration, resp: Responder<u64> },
+    CancelSchedule { id: u64, resp: Responder<bool> },
+    TopKReserve { key: String, k: usize, width: usize, depth: usize, resp: Responder<()> },
+    TopKAdd { key: String, items: Vec<String>, resp: Responder<()> },
+    TopKList { key: String, resp: Responder<Vec<(String, u64)>> },
+    UniqueReserve { key: String, bucket: Duration, retention: Duration, resp: Responder<()> },
+    LeaderboardOpen { key: String, reset_every: Option<Duration>, resp: Responder<()> },
+    LeaderboardAdd { key: String, member: String, delta: f64, resp: Responder<f64> },
+    LeaderboardTop { key: String, n: usize, resp: Responder<Vec<(String, f64)>> },
+    LeaderboardRank { key: String, member: String, resp: Responder<Option<usize>> },
+    LeaderboardAround { key: String, member: String, window: usize, resp: Responder<Vec<(String, f64)>> },
+    QueuePush { key: String, body: String, delay: Duration, resp: Responder<()> },
+    QueuePop { key: String, visibility: Duration, resp: Responder<Option<(u64, String)>> },
+    QueueAck { key: String, receipt: u64, resp: Responder<bool> },
+    QueueLen { key: String, resp: Responder<(usize, usize)> },
+    UniqueAdd { key: String, items: Vec<String>, resp: Responder<()> },
+    UniqueCount { key: String, window: Duration, resp: Responder<u64> },
+    Checkout { key: String, lease_ttl: Duration, resp: Responder<Option<String>> },
+    Lookup { key: CommandKey, resp: Responder<Lookup> },
+    SetNegative { key: String, ttl: Option<Duration>, resp: Responder<()> },
+    CheckAndRecord { key: String, value: String, ttl: Option<Duration>, resp: Responder<Option<String>> },
+    GetEx { key: CommandKey, expiry: Expiry, resp: Responder<Option<String>> },
+    GetRequired { key: CommandKey, resp: Responder<String> },
+    BulkSet { items: VecDeque<(String, String)>, atomic: bool, resp: Responder<()> },
+    SetMaxSize { max_size: Option<NonZeroUsize>, resp: Responder<()> },
+    ReloadConfig { config: Box<CacheConfig>, resp: Responder<Vec<&'static str>> },
+    BulkSetNx { items: Vec<(String, String)>, resp: Responder<bool> },
+    BulkGet { keys: Vec<String>, atomic: bool, results: Vec<Option<String>>, resp: Responder<Vec<Option<String>>> },
+    ObjectInfo { key: CommandKey, resp: Responder<Option<ObjectInfo>> },
+    Entries { order: EntryOrder, limit: usize, resp: Responder<Vec<(String, String)>> },
+    Scan { cursor: Option<String>, options: ScanOptions, resp: Responder<ScanPage> },
+    Snapshot { resp: Responder<Snapshot> },
+    Ping { resp: Responder<()> },
+    Len { resp: Responder<usize> },
+    DeltaSince { since: u64, resp: Responder<Delta> },
+    MergeDelta { delta: Delta, resp: Responder<usize> },
+    Flush { in_background: bool, resp: Responder<()> },
+    GetStats { resp: Responder<CacheStats> },
+    SubscribeAlarms { subscriber: mpsc::Sender<Alarm>, resp: Responder<()> },
+    UpdateTtl { key: CommandKey, ttl: Duration, resp: Responder<bool> },
+    UpdateTtlRequired { key: CommandKey, ttl: Duration, resp: Responder<()> },
+    CompareAndSwap { key: String, expected: String, new_value: String, resp: Responder<bool> },
+    IncrByFloat { key: String, delta: f64, bounds: Option<CounterBounds>, ttl: Option<Duration>, resp: Responder<f64> },
+    // Sent back by a value worker whose checksum check failed
+    EvictCorrupted { key: String, value: StoredValue },
     Shutdown,
 }
 
+impl CacheCommand {
+    fn name(&self) -> &'static str {
+        match self {
+            CacheCommand::Set { .. } => "set",
+            CacheCommand::SetWithDependencies { .. } => "set_with_dependencies",
+            CacheCommand::Get { .. } => "get",
+            CacheCommand::Delete { .. } => "delete",
+            CacheCommand::TryLease { .. } => "try_lease",
+            CacheCommand::ReleaseLease { .. } => "release_lease",
+            CacheCommand::Checkout { .. } => "checkout",
+            CacheCommand::Breaker { .. } => "breaker",
+            CacheCommand::ScheduleDelete { .. } => "schedule_delete",
+            CacheCommand::CancelSchedule { .. } => "cancel_schedule",
+            CacheCommand::TopKReserve { .. } => "topk_reserve",
+            CacheCommand::TopKAdd { .. } => "topk_add",
+            CacheCommand::TopKList { .. } => "topk_list",
+            CacheCommand::UniqueReserve { .. } => "unique_reserve",
+            CacheCommand::LeaderboardOpen { .. } => "leaderboard_open",
+            CacheCommand::LeaderboardAdd { .. } => "leaderboard_add",
+            CacheCommand::LeaderboardTop { .. } => "leaderboard_top",
+            CacheCommand::LeaderboardRank { .. } => "leaderboard_rank",
+            CacheCommand::LeaderboardAround { .. } => "leaderboard_around",
+            CacheCommand::QueuePush { .. } => "queue_push",
+            CacheCommand::QueuePop { .. } => "queue_pop",
+            CacheCommand::QueueAck { .. } => "queue_ack",
+            CacheCommand::QueueLen { .. } => "queue_len",
+            CacheCommand::UniqueAdd { .. } => "unique_add",
+            CacheCommand::UniqueCount { .. } => "unique_count",
+            CacheCommand::Lookup { .. } => "lookup",
+            CacheCommand::SetNegative { .. } => "set_negative",
+            CacheCommand::CheckAndRecord { .. } => "check_and_record",
+            CacheCommand::GetEx { .. } => "get_ex",
+            CacheCommand::GetRequired { .. } => "get_required",
+            CacheCommand::BulkSet { .. } => "bulk_set",
+            CacheCommand::SetMaxSize { .. } => "set_max_size",
+            CacheCommand::ReloadConfig { .. } => "reload_config",
+            CacheCommand::BulkSetNx { .. } => "bulk_set_nx",
+            CacheCommand::BulkGet { .. } => "bulk_get",
+            CacheCommand::ObjectInfo { .. } => "object_info",
+            CacheCommand::Entries { .. } => "entries",
+            CacheCommand::Scan { .. } => "scan",
+            CacheCommand::Snapshot { .. } => "snapshot",
+            CacheCommand::Ping { .. } => "ping",
+            CacheCommand::Len { .. } => "len",
+            CacheCommand::DeltaSince { .. } => "delta_since",
+            CacheCommand::MergeDelta { .. } => "merge_delta",
+            CacheCommand::Flush { .. } => "flush",
+            CacheCommand::GetStats { .. } => "get_stats",
+            CacheCommand::SubscribeAlarms { .. } => "subscribe_alarms",
+            CacheCommand::UpdateTtl { .. } => "update_ttl",
+            CacheCommand::UpdateTtlRequired { .. } => "update_ttl_required",
+            CacheCommand::CompareAndSwap { .. } => "compare_and_swap",
+            CacheCommand::IncrByFloat { .. } => "incr_by_float",
+            CacheCommand::EvictCorrupted { .. } => "evict_corrupted",
+            CacheCommand::Shutdown => "shutdown",
+        }
+    }
+
+    fn key(&self) -> Option<&str> {
+        match self {
+            CacheCommand::Set { key, .. }
+            | CacheCommand::SetWithDependencies { key, .. }
+            | CacheCommand::Delete { key, .. }
+            | CacheCommand::TryLease { key, .. }
+            | CacheCommand::CheckAndRecord { key, .. }
+            | CacheCommand::ReleaseLease { key, .. }
+            | CacheCommand::Checkout { key, .. }
+            | CacheCommand::Breaker { service: key, .. }
+            | CacheCommand::TopKReserve { key, .. }
+            | CacheCommand::TopKAdd { key, .. }
+            | CacheCommand::TopKList { key, .. }
+            | CacheCommand::UniqueReserve { key, .. }
+            | CacheCommand::UniqueAdd { key, .. }
+            | CacheCommand::UniqueCount { key, .. }
+            | CacheCommand::LeaderboardOpen { key, .. }
+            | CacheCommand::LeaderboardAdd { key, .. }
+            | CacheCommand::LeaderboardTop { key, .. }
+            | CacheCommand::LeaderboardRank { key, .. }
+            | CacheCommand::LeaderboardAround { key, .. }
+            | CacheCommand::QueuePush { key, .. }
+            | CacheCommand::QueuePop { key, .. }
+            | CacheCommand::QueueAck { key, .. }
+            | CacheCommand::QueueLen { key, .. }
+            | CacheCommand::CompareAndSwap { key, .. }
+            | CacheCommand::SetNegative { key, .. }
+            | CacheCommand::IncrByFloat { key, .. } => Some(key),
+            CacheCommand::Get { key, .. }
+            | CacheCommand::Lookup { key, .. }
+            | CacheCommand::GetEx { key, .. }
+            | CacheCommand::GetRequired { key, .. }
+            | CacheCommand::UpdateTtl { key, .. }
+            | CacheCommand::UpdateTtlRequired { key, .. }
+            | CacheCommand::ObjectInfo { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    // Rewrites every cache key the command carries, counting the ones that
+    // changed. Tracker and breaker names, glob patterns and scan cursors are
+    // left alone.
+    fn normalize_keys(&mut self, normalize: &KeyNormalizer, stats: &mut CacheStats) {
+        let mut canonical = |key: &str| {
+            let normalized = normalize(key);
+            if normalized != key {
+                stats.record_normalized(&normalized);
+            }
+            normalized
+        };
+        match self {
+            CacheCommand::Set { key, .. }
+            | CacheCommand::Delete { key, .. }
+            | CacheCommand::TryLease { key, .. }
+            | CacheCommand::ReleaseLease { key, .. }
+            | CacheCommand::Checkout { key, .. }
+            | CacheCommand::SetNegative { key, .. }
+            | CacheCommand::CheckAndRecord { key, .. }
+            | CacheCommand::CompareAndSwap { key, .. }
+            | CacheCommand::IncrByFloat { key, .. } => *key = canonical(key),
+            CacheCommand::SetWithDependencies { key, dependencies, .. } => {
+                *key = canonical(key);
+                dependencies.iter_mut().for_each(|dependency| *dependency = canonical(dependency));
+            },
+            CacheCommand::Get { key, .. }
+            | CacheCommand::Lookup { key, .. }
+            | CacheCommand::GetEx { key, .. }
+            | CacheCommand::GetRequired { key, .. }
+            | CacheCommand::ObjectInfo { key, .. }
+            | CacheCommand::UpdateTtl { key, .. }
+            | CacheCommand::UpdateTtlRequired { key, .. } => *key = canonical(key).into(),
+            CacheCommand::BulkSet { items, .. } => items.iter_mut().for_each(|(key, _)| *key = canonical(key)),
+            CacheCommand::BulkSetNx { items, .. } => items.iter_mut().for_each(|(key, _)| *key = canonical(key)),
+            CacheCommand::BulkGet { keys, .. } => keys.iter_mut().for_each(|key| *key = canonical(key)),
+            CacheCommand::MergeDelta { delta, .. } => delta.entries.iter_mut().for_each(|entry| entry.key = canonical(&entry.key)),
+            _ => {},
+        }
+    }
+
+    // Fails the command without running it
+    fn reject(self, error: CacheError) {
+        match self {
+            CacheCommand::Set { resp, .. } => resp.fail(error),
+            CacheCommand::Get { resp, .. } => resp.fail(error),
+            CacheCommand::SetWithDependencies { resp, .. } => resp.fail(error),
+            CacheCommand::Delete { resp, .. } => resp.fail(error),
+            CacheCommand::TryLease { resp, .. } => resp.fail(error),
+            CacheCommand::ReleaseLease { resp, .. } => resp.fail(error),
+            CacheCommand::Breaker { resp, .. } => resp.fail(error),
+            CacheCommand::ScheduleDelete { resp, .. } => resp.fail(error),
+            CacheCommand::CancelSchedule { resp, .. } => resp.fail(error),
+            CacheCommand::TopKReserve { resp, .. } => resp.fail(error),
+            CacheCommand::TopKAdd { resp, .. } => resp.fail(error),
+            CacheCommand::TopKList { resp, .. } => resp.fail(error),
+            CacheCommand::UniqueReserve { resp, .. } => resp.fail(error),
+            CacheCommand::UniqueAdd { resp, .. } => resp.fail(error),
+            CacheCommand::UniqueCount { resp, .. } => resp.fail(error),
+            CacheCommand::LeaderboardOpen { resp, .. } => resp.fail(error),
+            CacheCommand::LeaderboardAdd { resp, .. } => resp.fail(error),
+            CacheCommand::LeaderboardTop { resp, .. } => resp.fail(error),
+            CacheCommand::LeaderboardRank { resp, .. } => resp.fail(error),
+            CacheCommand::LeaderboardAround { resp, .. } => resp.fail(error),
+            CacheCommand::QueuePush { resp, .. } => resp.fail(error),
+            CacheCommand::QueuePop { resp, .. } => resp.fail(error),
+            CacheCommand::QueueAck { resp, .. } => resp.fail(error),
+            CacheCommand::QueueLen { resp, .. } => resp.fail(error),
+            CacheCommand::Checkout { resp, .. } => resp.fail(error),
+            CacheCommand::Lookup { resp, .. } => resp.fail(error),
+            CacheCommand::SetNegative { resp, .. } => resp.fail(error),
+            CacheCommand::CheckAndRecord { resp, .. } => resp.fail(error),
+            CacheCommand::GetEx { resp, .. } => resp.fail(error),
+            CacheCommand::GetRequired { resp, .. } => resp.fail(error),
+            CacheCommand::BulkSet { resp, .. } => resp.fail(error),
+            CacheCommand::SetMaxSize { resp, .. } => resp.fail(error),
+            CacheCommand::ReloadConfig { resp, .. } => resp.fail(error),
+            CacheCommand::BulkSetNx { resp, .. } => resp.fail(error),
+            CacheCommand::BulkGet { resp, .. } => resp.fail(error),
+            CacheCommand::ObjectInfo { resp, .. } => resp.fail(error),
+            CacheCommand::Entries { resp, .. } => resp.fail(error),
+            CacheCommand::Scan { resp, .. } => resp.fail(error),
+            CacheCommand::Snapshot { resp, .. } => resp.fail(error),
+            CacheCommand::Ping { resp, .. } => resp.fail(error),
+            CacheCommand::Len { resp, .. } => resp.fail(error),
+            CacheCommand::DeltaSince { resp, .. } => resp.fail(error),
+            CacheCommand::MergeDelta { resp, .. } => resp.fail(error),
+            CacheCommand::Flush { resp, .. } => resp.fail(error),
+            CacheCommand::GetStats { resp, .. } => resp.fail(error),
+            CacheCommand::SubscribeAlarms { resp, .. } => resp.fail(error),
+            CacheCommand::UpdateTtl { resp, .. } => resp.fail(error),
+            CacheCommand::UpdateTtlRequired { resp, .. } => resp.fail(error),
+            CacheCommand::CompareAndSwap { resp, .. } => resp.fail(error),
+            CacheCommand::IncrByFloat { resp, .. } => resp.fail(error),
+            CacheCommand::EvictCorrupted { .. } | CacheCommand::Shutdown => {},
+        }
+    }
+}
+
+// A recurring delete of every key matching `pattern`, run by the event
+// loop's timer
+struct Schedule {
+    id: u64,
+    pattern: String,
+    every: Duration,
+    next_run: Instant,
+}
+
+// Single-key reads go in a priority lane so they aren't stuck behind a large
+// bulk command. Only commands already waiting together are reordered: a caller
+// that waits for its write before reading still reads its own write.
+struct CommandQueue {
+    priority: VecDeque<Queued>,
+    normal: VecDeque<Queued>,
+    priority_streak: usize,
+}
+
+struct Queued {
+    queued_at: Instant,
+    deadline: Option<Instant>,
+    cmd: CacheCommand,
+}
+
+// What travels over the command channel: the command plus, when the sending
+// handle has a timeout, the instant after which it is no longer worth running
+struct Envelope {
+    cmd: CacheCommand,
+    deadline: Option<Instant>,
+}
+
+#[derive(Clone)]
+struct CommandSender {
+    channel: Sender<Envelope>,
+    timeout: Option<Duration>,
+}
+
+impl CommandSender {
+    fn send(&self, cmd: CacheCommand) -> Result<(), CacheError> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.channel.send(Envelope { cmd, deadline }).map_err(|_| CacheError::LockError)
+    }
+}
+
+impl CommandQueue {
+    fn new() -> Self {
+        CommandQueue {
+            priority: VecDeque::new(),
+            normal: VecDeque::new(),
+            priority_streak: 0,
+        }
+    }
+
+    fn push(&mut self, cmd: CacheCommand, deadline: Option<Instant>) {
+        let lane = match cmd {
+            CacheCommand::Get { .. }
+            | CacheCommand::Lookup { .. }
+            | CacheCommand::GetEx { .. }
+            | CacheCommand::GetRequired { .. }
+            | CacheCommand::ObjectInfo { .. } => &mut self.priority,
+            _ => &mut self.normal,
+        };
+        lane.push_back(Queued { queued_at: Instant::now(), deadline, cmd });
+    }
+
+    fn pop(&mut self) -> Option<Queued> {
+        if self.priority.is_empty() || self.priority_streak >= PRIORITY_BURST {
+            if let Some(queued) = self.normal.pop_front() {
+                self.priority_streak = 0;
+                return Some(queued);
+            }
+        }
+        let queued = self.priority.pop_front()?;
+        self.priority_streak += 1;
+        Some(queued)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.priority.is_empty() && self.normal.is_empty()
+    }
+}
+
 #[derive(Clone)]
 pub struct Cache {
-    sender: Sender<CacheCommand>,
-    event_loop_handle: Arc<Option<JoinHandle<()>>>,
-    running: Arc<AtomicBool>,
+    sender: CommandSender,
+    event_loop: Arc<EventLoopHandle>,
 }
 
 impl Cache {
-    pub fn new(config: CacheConfig) -> Self {
-        let (sender, receiver) = mpsc::channel();
-        let running = Arc::new(AtomicBool::new(true));
-        let running_clone = running.clone();
-        
-        let handle = thread::spawn(move || {
-            let _ = run_event_loop(receiver, config, running_clone);
-        });
-        
+    // Never fails: settings try_new would reject (see CacheConfig::validate)
+    // are replaced with working ones, logged when tracing is on, and listed
+    // in the stats' repaired_settings()
+    pub fn new(mut config: CacheConfig) -> Self {
+        let mut repaired = Vec::new();
+        if let Err(_e) = config.validate() {
+            repaired = config.repair();
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %_e, settings = ?repaired, "invalid cache config; using fallbacks for these settings");
+        }
+        // Not validated again: repair() already replaced everything it checks
+        Self::start(config, repaired)
+    }
+
+    pub fn try_new(config: CacheConfig) -> Result<Self, CacheError> {
+        config.validate()?;
+        Ok(Self::start(config, Vec::new()))
+    }
+
+    fn start(config: CacheConfig, repaired: Vec<&'static str>) -> Self {
+        let (channel, receiver) = transport::channel();
+        let sender = CommandSender { channel, timeout: None };
+        let shutdown_sender = sender.clone();
+        let loopback = sender.clone();
+
+        let event_loop = EventLoopHandle::spawn(
+            move |running| {
+                let _ = run_event_loop(receiver, loopback, config, repaired, running);
+            },
+            move || {
+                let _ = shutdown_sender.send(CacheCommand::Shutdown);
+            },
+        );
+
         Cache {
             sender,
-            event_loop_handle: Arc::new(Some(handle)),
-            running,
+            event_loop: Arc::new(event_loop),
+        }
+    }
+
+    // Another handle on the same cache whose commands are answered with
+    // Timeout, without running, if the event loop only gets to them more than
+    // `timeout` after they were sent. Under overload this sheds work whose
+    // caller has given up anyway. The caller still waits for that answer.
+    pub fn with_timeout(&self, timeout: Duration) -> Cache {
+        let mut cache = self.clone();
+        cache.sender.timeout = Some(timeout);
+        cache
+    }
+
+    pub fn set(&self, key: String, value: String, ttl: Option<Duration>) -> Result<(), CacheError> {
+        self.set_with_options(key, value, SetOptions { ttl, keep_ttl: None })
+    }
+
+    pub fn set_with_options(&self, key: String, value: String, options: SetOptions) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Set { key, value, options, resp: Responder::Channel(resp_sender) })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Setting, deleting or otherwise changing any of `dependencies` later
+    // drops this entry too, along with anything that depends on it in turn
+    pub fn set_with_dependencies(&self, key: String, value: String, ttl: Option<Duration>, dependencies: Vec<String>) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::SetWithDependencies { key, value, ttl, dependencies, resp: Responder::Channel(resp_sender) })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Get { 
+            key: key.into(), 
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Returns whether the key was present; entries depending on it are dropped either way
+    pub fn delete(&self, key: &str) -> Result<bool, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Delete {
+            key: key.to_string(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn set_with_slot(&self, key: String, value: String, ttl: Option<Duration>, slot: &ResponseSlot<()>) -> Result<(), CacheError> {
+        let options = SetOptions { ttl, keep_ttl: None };
+        let (resp_sender, resp_receiver) = slot.take();
+        self.sender.send(CacheCommand::Set { key, value, options, resp: Responder::Slot(resp_sender) })?;
+        slot.wait(resp_receiver)
+    }
+
+    pub fn get_with_slot(&self, key: &str, slot: &ResponseSlot<Option<String>>) -> Result<Option<String>, CacheError> {
+        let (resp_sender, resp_receiver) = slot.take();
+        self.sender.send(CacheCommand::Get {
+            key: key.into(),
+            resp: Responder::Slot(resp_sender),
+        })?;
+        slot.wait(resp_receiver)
+    }
+
+    // Callbacks run on the event loop thread, except for reads of large values
+    // finished by value workers (see CacheConfig::with_value_workers), whose
+    // callbacks run on a worker. Keep them short and never call back into the
+    // cache from inside one: on the event loop thread that deadlocks.
+    pub fn set_with_callback<F>(&self, key: String, value: String, ttl: Option<Duration>, callback: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(Result<(), CacheError>) + Send + 'static,
+    {
+        let options = SetOptions { ttl, keep_ttl: None };
+        self.sender.send(CacheCommand::Set { key, value, options, resp: Responder::Callback(Box::new(callback)) })
+    }
+
+    pub fn get_with_callback<F>(&self, key: &str, callback: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(Result<Option<String>, CacheError>) + Send + 'static,
+    {
+        self.sender.send(CacheCommand::Get {
+            key: key.into(),
+            resp: Responder::Callback(Box::new(callback)),
+        })
+    }
+
+    pub fn lookup(&self, key: &str) -> Result<Lookup, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Lookup {
+            key: key.into(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Records that `key` doesn't exist upstream, so lookups return
+    // NotFoundCached until the TTL (default: the config's negative TTL) runs
+    // out or the key is written
+    pub fn set_negative(&self, key: String, ttl: Option<Duration>) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::SetNegative {
+            key,
+            ttl,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Lets one worker at a time regenerate `key`: true means the caller holds
+    // the lease and should recompute and set the value, false means someone
+    // else already is. Writing the key ends the lease; so does `ttl` running
+    // out, in case the holder dies.
+    pub fn try_lease(&self, key: &str, ttl: Duration) -> Result<bool, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::TryLease {
+            key: key.to_string(),
+            ttl,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Gives up a lease without writing the key, e.g. when recomputing failed
+    pub fn release_lease(&self, key: &str) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::ReleaseLease {
+            key: key.to_string(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Borrows a pooled value exclusively: later checkouts get None until it is
+    // checked back in or `lease_ttl` lapses, at which point the stored value is
+    // available again. Shares its lease with try_lease, and plain gets still
+    // read the value.
+    pub fn checkout(&self, key: &str, lease_ttl: Duration) -> Result<Option<String>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Checkout {
+            key: key.to_string(),
+            lease_ttl,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Returns a checked-out value, possibly updated, keeping the entry's TTL
+    pub fn checkin(&self, key: String, value: String) -> Result<(), CacheError> {
+        self.set_with_options(key, value, SetOptions { ttl: None, keep_ttl: Some(true) })
+    }
+
+    // Circuit breaker shared by every worker using this cache. Call before
+    // each request to `service`: Closed means go ahead, Open means fail fast,
+    // and HalfOpen means this caller gets the single trial request.
+    pub fn breaker_check(&self, service: &str, policy: BreakerPolicy) -> Result<BreakerState, CacheError> {
+        self.send_breaker_event(service, BreakerEvent::Check, policy)
+    }
+
+    pub fn breaker_record_success(&self, service: &str, policy: BreakerPolicy) -> Result<BreakerState, CacheError> {
+        self.send_breaker_event(service, BreakerEvent::Success, policy)
+    }
+
+    pub fn breaker_record_failure(&self, service: &str, policy: BreakerPolicy) -> Result<BreakerState, CacheError> {
+        self.send_breaker_event(service, BreakerEvent::Failure, policy)
+    }
+
+    fn send_breaker_event(&self, service: &str, event: BreakerEvent, policy: BreakerPolicy) -> Result<BreakerState, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Breaker {
+            service: service.to_string(),
+            event,
+            policy,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Deletes every key matching the glob `pattern` once per `every`, first
+    // run one period from now. Returns an id for cancel_schedule. Schedules
+    // do not survive a restart.
+    pub fn schedule_delete(&self, pattern: &str, every: Duration) -> Result<u64, CacheError> {
+        if every.is_zero() {
+            return Err(CacheError::ConfigError("schedule period must be non-zero".to_string()));
+        }
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::ScheduleDelete {
+            pattern: pattern.to_string(),
+            every,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn cancel_schedule(&self, id: u64) -> Result<bool, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::CancelSchedule { id, resp: Responder::Channel(resp_sender) })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Creates (or resets) a top-k tracker: a `depth` x `width` count-min
+    // sketch plus the names of the k most frequent items. Wider sketches
+    // overcount less; memory stays fixed however many distinct items arrive.
+    pub fn topk_reserve(&self, key: &str, k: usize, width: usize, depth: usize) -> Result<(), CacheError> {
+        if k == 0 || width == 0 || depth == 0 {
+            return Err(CacheError::ConfigError("top-k k, width and depth must be non-zero".to_string()));
+        }
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::TopKReserve {
+            key: key.to_string(),
+            k,
+            width,
+            depth,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn topk_add<I>(&self, key: &str, items: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::TopKAdd {
+            key: key.to_string(),
+            items: items.into_iter().collect(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Tracked items with their estimated counts, most frequent first
+    pub fn topk_list(&self, key: &str) -> Result<Vec<(String, u64)>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::TopKList {
+            key: key.to_string(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Creates (or resets) a unique counter: one HyperLogLog sketch (16 KiB,
+    // about 0.8% error) per `bucket` of time, kept for `retention`
+    pub fn unique_reserve(&self, key: &str, bucket: Duration, retention: Duration) -> Result<(), CacheError> {
+        if bucket.is_zero() || retention.is_zero() {
+            return Err(CacheError::ConfigError("unique counter bucket and retention must be non-zero".to_string()));
+        }
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::UniqueReserve {
+            key: key.to_string(),
+            bucket,
+            retention,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn unique_add<I>(&self, key: &str, items: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::UniqueAdd {
+            key: key.to_string(),
+            items: items.into_iter().collect(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Estimated distinct items added in the last `window`, rounded up to
+    // whole buckets (the current one included) and capped at the retention.
+    // The buckets are merged on the event loop.
+    pub fn unique_count(&self, key: &str, window: Duration) -> Result<u64, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::UniqueCount {
+            key: key.to_string(),
+            window,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Backs Leaderboard; see there
+    pub(crate) fn leaderboard_open(&self, key: &str, reset_every: Option<Duration>) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::LeaderboardOpen {
+            key: key.to_string(),
+            reset_every,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn leaderboard_add(&self, key: &str, member: &str, delta: f64) -> Result<f64, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::LeaderboardAdd {
+            key: key.to_string(),
+            member: member.to_string(),
+            delta,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn leaderboard_top(&self, key: &str, n: usize) -> Result<Vec<(String, f64)>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::LeaderboardTop {
+            key: key.to_string(),
+            n,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn leaderboard_rank(&self, key: &str, member: &str) -> Result<Option<usize>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::LeaderboardRank {
+            key: key.to_string(),
+            member: member.to_string(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn leaderboard_around(&self, key: &str, member: &str, window: usize) -> Result<Vec<(String, f64)>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::LeaderboardAround {
+            key: key.to_string(),
+            member: member.to_string(),
+            window,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Backs MessageQueue; see there. A zero delay makes the message visible
+    // at once.
+    pub(crate) fn queue_push(&self, key: &str, body: String, delay: Duration) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::QueuePush {
+            key: key.to_string(),
+            body,
+            delay,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn queue_pop(&self, key: &str, visibility: Duration) -> Result<Option<(u64, String)>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::QueuePop {
+            key: key.to_string(),
+            visibility,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn queue_ack(&self, key: &str, receipt: u64) -> Result<bool, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::QueueAck {
+            key: key.to_string(),
+            receipt,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub(crate) fn queue_len(&self, key: &str) -> Result<(usize, usize), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::QueueLen {
+            key: key.to_string(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Returns the response recorded for an idempotency key, or records
+    // `response` and returns None if there isn't one yet. Lookup and record
+    // happen in one step, so concurrent retries agree on a single response.
+    pub fn check_and_record_idempotency(&self, key: &str, response: String, ttl: Option<Duration>) -> Result<Option<String>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::CheckAndRecord {
+            key: key.to_string(),
+            value: response,
+            ttl,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn pset(&self, key: String, value: String, ttl_ms: u64) -> Result<(), CacheError> {
+        self.set(key, value, Some(Duration::from_millis(ttl_ms)))
+    }
+
+    pub fn get_ex(&self, key: &str, expiry: Expiry) -> Result<Option<String>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::GetEx {
+            key: key.into(),
+            expiry,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn get_required(&self, key: &str) -> Result<String, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::GetRequired {
+            key: key.into(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Large batches run in chunks interleaved with other clients' commands, so
+    // readers may observe a partially applied batch
+    pub fn bulk_set<I>(&self, items: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.send_bulk_set(items, false)
+    }
+
+    pub fn bulk_set_atomic<I>(&self, items: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.send_bulk_set(items, true)
+    }
+
+    fn send_bulk_set<I>(&self, items: I, atomic: bool) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let items: VecDeque<_> = items.into_iter().collect();
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::BulkSet { 
+            items, 
+            atomic,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn set_max_size(&self, max_size: Option<NonZeroUsize>) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::SetMaxSize {
+            max_size,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Applies a new config to the running cache. Returns the settings that
+    // differed but only take effect on a new instance; those keep their old values.
+    pub fn reload_config(&self, config: CacheConfig) -> Result<Vec<&'static str>, CacheError> {
+        config.validate()?;
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::ReloadConfig {
+            config: Box::new(config),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // All-or-nothing: sets every item only if none of the keys exist
+    pub fn bulk_set_nx<I>(&self, items: I) -> Result<bool, CacheError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let items_vec: Vec<_> = items.into_iter().collect();
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::BulkSetNx {
+            items: items_vec,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Like bulk_set, large key lists are looked up in chunks; use
+    // bulk_get_atomic when all values must come from the same moment
+    pub fn bulk_get<I, T>(&self, keys: I) -> Result<HashMap<T, Option<String>>, CacheError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String> + Clone + std::hash::Hash + Eq,
+    {
+        self.send_bulk_get(keys, false)
+    }
+
+    pub fn bulk_get_atomic<I, T>(&self, keys: I) -> Result<HashMap<T, Option<String>>, CacheError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String> + Clone + std::hash::Hash + Eq,
+    {
+        self.send_bulk_get(keys, true)
+    }
+
+    fn send_bulk_get<I, T>(&self, keys: I, atomic: bool) -> Result<HashMap<T, Option<String>>, CacheError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String> + Clone + std::hash::Hash + Eq,
+    {
+        let keys_vec: Vec<T> = keys.into_iter().collect();
+        let keys_string: Vec<String> = keys_vec.iter().map(|k| k.clone().into()).collect();
+        
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::BulkGet { 
+            keys: keys_string, 
+            atomic,
+            results: Vec::new(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        
+        // / Values come back in the same order as the keys were sent
+        let values = resp_receiver.recv().map_err(|_| CacheError::LockError)??;
+        let mut converted_result = HashMap::with_capacity(keys_vec.len());
+        converted_result.extend(keys_vec.into_iter().zip(values));
+
+        Ok(converted_result)
+    }
+
+    pub fn warm_from<I, F>(&self, source: I, concurrency: NonZeroUsize, on_progress: F) -> Result<usize, CacheError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+        I::IntoIter: Send,
+        F: Fn(usize) + Sync,
+    {
+        let source = Mutex::new(source.into_iter());
+        let loaded = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..concurrency.get())
+                .map(|_| scope.spawn(|| -> Result<(), CacheError> {
+                    loop {
+                        let batch: Vec<_> = {
+                            let mut source = source.lock().map_err(|_| CacheError::LockError)?;
+                            source.by_ref().take(WARM_BATCH_SIZE).collect()
+                        };
+                        if batch.is_empty() {
+                            return Ok(());
+                        }
+
+                        let count = batch.len();
+                        self.bulk_set(batch)?;
+                        on_progress(loaded.fetch_add(count, Ordering::Relaxed) + count);
+                    }
+                }))
+                .collect();
+
+            workers.into_iter()
+                .try_for_each(|worker| worker.join().unwrap_or(Err(CacheError::LockError)))
+        })?;
+
+        Ok(loaded.into_inner())
+    }
+
+    pub fn object_info(&self, key: &str) -> Result<Option<ObjectInfo>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::ObjectInfo {
+            key: key.into(),
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn entries(&self, order: EntryOrder, limit: usize) -> Result<Vec<(String, String)>, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Entries {
+            order,
+            limit,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn scan(&self, cursor: Option<&str>, options: ScanOptions) -> Result<ScanPage, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Scan {
+            cursor: cursor.map(str::to_string),
+            options,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn snapshot(&self) -> Result<Snapshot, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Snapshot {
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Walks a snapshot rather than the live map, so the event loop is only
+    // held up for the copy
+    pub fn analyze(&self) -> Result<KeyspaceReport, CacheError> {
+        Ok(self.snapshot()?.analyze())
+    }
+
+    pub fn ping(&self) -> Result<Duration, CacheError> {
+        let started = Instant::now();
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Ping {
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)??;
+        Ok(started.elapsed())
+    }
+
+    // Includes entries whose TTL passed moments ago and that cleanup hasn't
+    // removed yet, like Redis' DBSIZE
+    pub fn len(&self) -> Result<usize, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Len {
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn is_empty(&self) -> Result<bool, CacheError> {
+        Ok(self.len()? == 0)
+    }
+
+    pub fn flush(&self) -> Result<(), CacheError> {
+        self.send_flush(false)
+    }
+
+    // Empties the cache immediately but frees the old entries on a background
+    // thread, so flushing a large cache doesn't stall other commands
+    pub fn flush_async(&self) -> Result<(), CacheError> {
+        self.send_flush(true)
+    }
+
+    fn send_flush(&self, in_background: bool) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::Flush {
+            in_background,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Live entries written at or after `since` (0 for everything), to ship to a
+    // peer cache's merge_delta
+    pub fn delta_since(&self, since: u64) -> Result<Delta, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::DeltaSince {
+            since,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Applies the entries that win last-writer-wins against local ones and
+    // returns how many did
+    pub fn merge_delta(&self, delta: Delta) -> Result<usize, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::MergeDelta {
+            delta,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Every live entry as of now, to serve in chunks of `chunk_size` bytes to
+    // a follower or a backup client. Taken in one step on the event loop, so
+    // it is consistent; encoding and serving it don't involve the loop or disk.
+    pub fn snapshot_stream(&self, chunk_size: usize) -> Result<SnapshotStream, CacheError> {
+        self.delta_since(0).map(|delta| SnapshotStream::new(delta, chunk_size))
+    }
+
+    pub fn get_stats(&self) -> Result<CacheStats, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::GetStats { 
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Alarms configured with CacheConfig::with_alarms arrive on the returned
+    // channel; dropping it unsubscribes
+    pub fn subscribe_alarms(&self) -> Result<mpsc::Receiver<Alarm>, CacheError> {
+        let (subscriber, alarms) = mpsc::channel();
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::SubscribeAlarms { subscriber, resp: Responder::Channel(resp_sender) })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)??;
+        Ok(alarms)
+    }
+
+    pub fn update_ttl(&self, key: &str, ttl: Duration) -> Result<bool, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::UpdateTtl { 
+            key: key.into(), 
+            ttl, 
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn pexpire(&self, key: &str, ttl_ms: u64) -> Result<bool, CacheError> {
+        self.update_ttl(key, Duration::from_millis(ttl_ms))
+    }
+
+    pub fn update_ttl_required(&self, key: &str, ttl: Duration) -> Result<(), CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::UpdateTtlRequired {
+            key: key.into(),
+            ttl,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn compare_and_swap(&self, key: &str, expected: &str, new_value: String) -> Result<bool, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::CompareAndSwap { 
+            key: key.to_string(), 
+            expected: expected.to_string(), 
+            new_value, 
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    pub fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, CacheError> {
+        self.send_incr_by_float(key, delta, None, None)
+    }
+
+    // `ttl` applies only when this increment creates the counter, so a
+    // counter keeps the expiry it was created with as it keeps counting
+    pub fn incr_by_float_with_ttl(&self, key: &str, delta: f64, ttl: Duration) -> Result<f64, CacheError> {
+        self.send_incr_by_float(key, delta, None, Some(ttl))
+    }
+
+    pub fn incr_by_float_bounded(&self, key: &str, delta: f64, bounds: CounterBounds) -> Result<f64, CacheError> {
+        self.send_incr_by_float(key, delta, Some(bounds), None)
+    }
+
+    fn send_incr_by_float(&self, key: &str, delta: f64, bounds: Option<CounterBounds>, ttl: Option<Duration>) -> Result<f64, CacheError> {
+        let (resp_sender, resp_receiver) = transport::response_channel();
+        self.sender.send(CacheCommand::IncrByFloat {
+            key: key.to_string(),
+            delta,
+            bounds,
+            ttl,
+            resp: Responder::Channel(resp_sender),
+        })?;
+        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    }
+
+    // Stops the cache for every clone; dropping the last clone does this too
+    pub(crate) fn request_shutdown(&self) {
+        self.event_loop.request_shutdown();
+    }
+
+    // Waits for a stopped event loop to finish, and persist, even while other
+    // clones are alive
+    pub(crate) fn join(&self) {
+        self.event_loop.join();
+    }
+}
+
+#[cfg(not(feature = "persistent-map"))]
+fn new_entry_map(config: &CacheConfig) -> EntryMap {
+    EntryMap::with_capacity_and_hasher(config.initial_capacity, KeyHashBuilder::new(config.hash_algorithm))
+}
+
+#[cfg(feature = "persistent-map")]
+fn new_entry_map(config: &CacheConfig) -> EntryMap {
+    EntryMap::with_hasher(KeyHashBuilder::new(config.hash_algorithm))
+}
+
+fn run_event_loop(
+    receiver: Receiver<Envelope>,
+    loopback: CommandSender,
+    mut config: CacheConfig,
+    repaired: Vec<&'static str>,
+    running: RunningFlag,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "testing")]
+    clock::install(config.clock.clone());
+    let mut data = new_entry_map(&config);
+    let mut expiration_queue = BinaryHeap::new();
+    let mut stats = CacheStats { repaired_settings: repaired, ..CacheStats::default() };
+    stats.track_prefixes(&config.tracked_prefixes);
+    let mut command_queue = CommandQueue::new();
+    let mut interned = HashSet::new();
+    let mut negative: HashMap<String, Instant> = HashMap::new();
+    let mut dependencies = DependencyGraph::default();
+    let mut leases: HashMap<String, Instant> = HashMap::new();
+    let mut breakers: HashMap<String, Breaker> = HashMap::new();
+    let mut sketches: HashMap<String, TopK> = HashMap::new();
+    let mut uniques: HashMap<String, UniqueCounter> = HashMap::new();
+    let mut boards: HashMap<String, Board> = HashMap::new();
+    let mut queues: HashMap<String, Queue> = HashMap::new();
+    // Shared by every queue and kept across flushes; see Queue
+    let mut next_receipt = 0;
+    let mut schedules: Vec<Schedule> = Vec::new();
+    let mut next_schedule_id = 1;
+    let mut alarms = AlarmMonitor::new();
+    let mut scan_index: Option<ScanIndex> = None;
+    let (mut ghosts, mut policy_ghosts) = new_ghosts(&config);
+    let workers = (config.value_workers > 0).then(|| WorkerPool::new(config.value_workers));
+
+    // A missing or unreadable snapshot should not keep the cache from starting
+    #[cfg(feature = "persistence")]
+    if let Some(path) = &config.persistence_path {
+        match persistence::load_snapshot(path, &mut data, &mut expiration_queue) {
+            Ok(()) => {
+                if config.checksums {
+                    for (_, entry) in data.iter_mut() {
+                        entry.checksum = Some(entry.value.checksum());
+                    }
+                }
+                #[cfg(feature = "tracing")]
+                tracing::info!(path = %path.display(), entries = data.len(), "loaded snapshot");
+            },
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(path = %path.display(), error = %_e, "discarding unreadable snapshot");
+                data.clear();
+                expiration_queue.clear();
+            },
+        }
+    }
+    
+    let mut last_cleanup = clock::now();
+    let mut last_wake = Instant::now();
+    
+    'event_loop: while running.is_set() {
+        // Process any pending commands
+        while let Ok(Envelope { cmd, deadline }) = receiver.try_recv() {
+            match cmd {
+                CacheCommand::Shutdown => break 'event_loop,
+                cmd => command_queue.push(admit(cmd, &config, &mut ghosts, &mut stats), deadline),
+            }
+        }
+        
+        // Process one command from the queue
+        if let Some(Queued { queued_at, deadline, cmd }) = command_queue.pop() {
+            let started = Instant::now();
+            let queue_wait = started.duration_since(queued_at);
+            let timed_out = deadline.is_some_and(|deadline| started > deadline);
+
+            let command_name = cmd.name();
+            let rejection = if timed_out { None } else { before_command(&config, command_name, &cmd) };
+            // Only copied out when a hook will need it after the command is consumed
+            let hook_key = if config.middleware.is_empty() { None } else { cmd.key().map(str::to_string) };
+            #[cfg(feature = "tracing")]
+            let span = tracing::debug_span!(
+                "cache_command",
+                command = command_name,
+                key = cmd.key(),
+                queue_wait_us = queue_wait.as_micros() as u64,
+                handler_us = tracing::field::Empty,
+                outcome = tracing::field::Empty,
+            ).entered();
+
+            let outcome = match cmd {
+                // The caller has given up; answering now frees the loop for
+                // commands that can still make it
+                cmd if timed_out => {
+                    cmd.reject(CacheError::Timeout);
+                    stats.timed_out += 1;
+                    "timed_out"
+                },
+                cmd if rejection.is_some() => {
+                    if let Some(error) = rejection {
+                        cmd.reject(error);
+                    }
+                    "rejected"
+                },
+                CacheCommand::Set { key, value, options, resp } => {
+                    let written = has_marks(&negative, &leases, &dependencies).then(|| key.clone());
+                    let value = intern_value(&mut interned, &config, value);
+                    let result = handle_set(&mut data, &mut expiration_queue, &config, key, value, options, &mut stats);
+                    if let (Ok(()), Some(key)) = (&result, written) {
+                        forget_marks(&mut negative, &mut leases, [key.as_str()]);
+                        invalidate_dependents(&mut data, &mut dependencies, [key.as_str()], &mut stats);
+                    }
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::SetWithDependencies { key, value, ttl, dependencies: depends_on, resp } => {
+                    let value = intern_value(&mut interned, &config, value);
+                    let options = SetOptions { ttl, keep_ttl: None };
+                    let result = handle_set(&mut data, &mut expiration_queue, &config, key.clone(), value, options, &mut stats);
+                    if result.is_ok() {
+                        forget_marks(&mut negative, &mut leases, [key.as_str()]);
+                        // Drops the key's old edges, so the new ones go in after
+                        invalidate_dependents(&mut data, &mut dependencies, [key.as_str()], &mut stats);
+                        dependencies.set_dependencies(&key, depends_on);
+                    }
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::Delete { key, resp } => {
+                    let removed = handle_delete(&mut data, &mut dependencies, &mut negative, &mut leases, &key, &mut stats);
+                    resp.send(Ok(removed));
+                    if removed { "hit" } else { "miss" }
+                },
+                CacheCommand::TryLease { key, ttl, resp } => {
+                    let acquired = handle_try_lease(&mut leases, key, ttl);
+                    resp.send(Ok(acquired));
+                    if acquired { "acquired" } else { "held" }
+                },
+                CacheCommand::ReleaseLease { key, resp } => {
+                    leases.remove(&key);
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::Breaker { service, event, policy, resp } => {
+                    let state = breakers.entry(service).or_default().apply(event, &policy, clock::now());
+                    resp.send(Ok(state));
+                    match state {
+                        BreakerState::Closed => "closed",
+                        BreakerState::Open => "open",
+                        BreakerState::HalfOpen => "half_open",
+                    }
+                },
+                CacheCommand::ScheduleDelete { pattern, every, resp } => {
+                    schedules.push(Schedule { id: next_schedule_id, pattern, every, next_run: clock::now() + every });
+                    resp.send(Ok(next_schedule_id));
+                    next_schedule_id += 1;
+                    "ok"
+                },
+                CacheCommand::CancelSchedule { id, resp } => {
+                    let before = schedules.len();
+                    schedules.retain(|schedule| schedule.id != id);
+                    let cancelled = schedules.len() < before;
+                    resp.send(Ok(cancelled));
+                    if cancelled { "ok" } else { "miss" }
+                },
+                CacheCommand::TopKReserve { key, k, width, depth, resp } => {
+                    sketches.insert(key, TopK::new(k, width, depth));
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::TopKAdd { key, items, resp } => {
+                    let result = match sketches.get_mut(&key) {
+                        Some(sketch) => {
+                            items.iter().for_each(|item| sketch.add(item));
+                            Ok(())
+                        },
+                        None => Err(CacheError::KeyNotFound),
+                    };
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::TopKList { key, resp } => {
+                    let result = sketches.get(&key).map(TopK::list).ok_or(CacheError::KeyNotFound);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::UniqueReserve { key, bucket, retention, resp } => {
+                    uniques.insert(key, UniqueCounter::new(bucket, retention, clock::now()));
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::UniqueAdd { key, items, resp } => {
+                    let result = match uniques.get_mut(&key) {
+                        Some(counter) => {
+                            let now = clock::now();
+                            items.iter().for_each(|item| counter.add(item, now));
+                            Ok(())
+                        },
+                        None => Err(CacheError::KeyNotFound),
+                    };
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::UniqueCount { key, window, resp } => {
+                    let result = uniques.get(&key)
+                        .map(|counter| counter.count(window, clock::now()))
+                        .ok_or(CacheError::KeyNotFound);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::LeaderboardOpen { key, reset_every, resp } => {
+                    boards.entry(key).or_insert_with(|| Board::new(reset_every, clock::now()));
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::LeaderboardAdd { key, member, delta, resp } => {
+                    let result = match current_board(&mut boards, &key) {
+                        Some(board) => board.add(&member, delta),
+                        None => Err(CacheError::KeyNotFound),
+                    };
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::LeaderboardTop { key, n, resp } => {
+                    let result = current_board(&mut boards, &key).map(|board| board.top(n)).ok_or(CacheError::KeyNotFound);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::LeaderboardRank { key, member, resp } => {
+                    let result = current_board(&mut boards, &key).map(|board| board.rank(&member)).ok_or(CacheError::KeyNotFound);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::LeaderboardAround { key, member, window, resp } => {
+                    let result = current_board(&mut boards, &key).map(|board| board.around(&member, window)).ok_or(CacheError::KeyNotFound);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::QueuePush { key, body, delay, resp } => {
+                    let queue = queues.entry(key).or_default();
+                    if delay.is_zero() {
+                        queue.push(body);
+                    } else {
+                        queue.push_at(body, clock::now() + delay);
+                    }
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::QueuePop { key, visibility, resp } => {
+                    let message = queues.get_mut(&key).and_then(|queue| queue.pop(visibility, clock::now(), &mut next_receipt));
+                    let outcome = if message.is_some() { "hit" } else { "miss" };
+                    resp.send(Ok(message));
+                    outcome
+                },
+                CacheCommand::QueueAck { key, receipt, resp } => {
+                    let acked = queues.get_mut(&key).is_some_and(|queue| queue.ack(receipt));
+                    resp.send(Ok(acked));
+                    if acked { "ok" } else { "miss" }
+                },
+                CacheCommand::QueueLen { key, resp } => {
+                    let len = queues.get_mut(&key).map_or((0, 0), |queue| {
+                        queue.requeue_lapsed(clock::now());
+                        queue.len()
+                    });
+                    resp.send(Ok(len));
+                    "ok"
+                },
+                CacheCommand::Checkout { key, lease_ttl, resp } => {
+                    let result = handle_checkout(&mut data, &mut leases, key, lease_ttl, &mut stats);
+                    let outcome = match &result {
+                        Ok(Some(_)) => "checked_out",
+                        Ok(None) => "unavailable",
+                        Err(_) => "error",
+                    };
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::Get { key, resp } => {
+                    let offload = match &workers {
+                        Some(workers) => large_hit(&mut data, &key, &mut stats).map(|hit| (workers, hit)),
+                        None => None,
+                    };
+                    if let Some((workers, (value, checksum))) = offload {
+                        let key = key.to_string();
+                        let loopback = loopback.clone();
+                        workers.execute(move || {
+                            if checksum.is_some_and(|checksum| checksum != value.checksum()) {
+                                let _ = loopback.send(CacheCommand::EvictCorrupted { key: key.clone(), value });
+                                resp.send(Err(CacheError::CorruptedEntry(key)));
+                            } else {
+                                resp.send(Ok(Some(value.to_string())));
+                            }
+                        });
+                        "hit"
+                    } else {
+                        let result = handle_get(&mut data, &key, &mut stats);
+                        let outcome = lookup_outcome(&result);
+                        resp.send(result);
+                        outcome
+                    }
+                },
+                CacheCommand::Lookup { key, resp } => {
+                    let result = handle_lookup(&mut data, &mut negative, &key, &mut stats);
+                    let outcome = match &result {
+                        Ok(Lookup::Hit(_)) => "hit",
+                        Ok(Lookup::NotFoundCached) => "negative_hit",
+                        Ok(Lookup::Miss) => "miss",
+                        Err(_) => "error",
+                    };
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::SetNegative { key, ttl, resp } => {
+                    invalidate_dependents(&mut data, &mut dependencies, [key.as_str()], &mut stats);
+                    handle_set_negative(&mut data, &mut negative, &config, key, ttl);
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::CheckAndRecord { key, value, ttl, resp } => {
+                    let value = intern_value(&mut interned, &config, value);
+                    let result = handle_check_and_record(&mut data, &mut expiration_queue, &config, &key, value, ttl, &mut stats);
+                    if let Ok(None) = result {
+                        forget_marks(&mut negative, &mut leases, [key.as_str()]);
+                        invalidate_dependents(&mut data, &mut dependencies, [key.as_str()], &mut stats);
+                    }
+                    let outcome = match &result {
+                        Ok(Some(_)) => "replayed",
+                        Ok(None) => "recorded",
+                        Err(_) => "error",
+                    };
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::GetEx { key, expiry, resp } => {
+                    let result = handle_get_ex(&mut data, &mut expiration_queue, &key, expiry, &mut stats);
+                    let outcome = lookup_outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::GetRequired { key, resp } => {
+                    let result = handle_get_required(&mut data, &key, &mut stats);
+                    let outcome = match &result {
+                        Ok(_) => "hit",
+                        Err(CacheError::KeyNotFound | CacheError::KeyExpired) => "miss",
+                        Err(_) => "error",
+                    };
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::BulkSet { mut items, atomic, resp } => {
+                    let chunk_size = if atomic { items.len() } else { config.bulk_chunk_size.get() };
+                    let chunk = take_chunk(&mut items, chunk_size);
+                    forget_marks(&mut negative, &mut leases, chunk.iter().map(|(key, _)| key.as_str()));
+                    invalidate_dependents(&mut data, &mut dependencies, chunk.iter().map(|(key, _)| key.as_str()), &mut stats);
+                    let result = handle_bulk_set(&mut data, &mut expiration_queue, &mut interned, &config, chunk, &mut stats);
+                    let outcome = outcome(&result);
+                    if result.is_ok() && !items.is_empty() {
+                        // Requeue the rest behind whatever arrived meanwhile. A
+                        // started bulk command runs to the end, whatever its deadline.
+                        command_queue.push(CacheCommand::BulkSet { items, atomic, resp }, None);
+                    } else {
+                        resp.send(result);
+                    }
+                    outcome
+                },
+                CacheCommand::ReloadConfig { config: new_config, resp } => {
+                    let simulated = simulated_settings(&config);
+                    let restart_required = reload_config(&mut config, *new_config);
+                    stats.track_prefixes(&config.tracked_prefixes);
+                    if simulated_settings(&config) != simulated {
+                        (ghosts, policy_ghosts) = new_ghosts(&config);
+                    }
+                    #[cfg(feature = "tracing")]
+                    if !restart_required.is_empty() {
+                        tracing::warn!(settings = ?restart_required, "config reloaded; some settings need a restart");
+                    }
+                    let result = enforce_max_size(&mut data, &mut expiration_queue, &config, &mut stats).map(|()| restart_required);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::SetMaxSize { max_size, resp } => {
+                    if max_size != config.max_size {
+                        config.max_size = max_size;
+                        (ghosts, policy_ghosts) = new_ghosts(&config);
+                    }
+                    let result = enforce_max_size(&mut data, &mut expiration_queue, &config, &mut stats);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::BulkSetNx { items, resp } => {
+                    let keys: Vec<String> = if has_marks(&negative, &leases, &dependencies) {
+                        items.iter().map(|(key, _)| key.clone()).collect()
+                    } else {
+                        Vec::new()
+                    };
+                    let result = handle_bulk_set_nx(&mut data, &mut expiration_queue, &mut interned, &config, items, &mut stats);
+                    // Nothing is written when any key already exists
+                    if let Ok(true) = result {
+                        forget_marks(&mut negative, &mut leases, keys.iter().map(String::as_str));
+                        invalidate_dependents(&mut data, &mut dependencies, keys.iter().map(String::as_str), &mut stats);
+                    }
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::BulkGet { keys, atomic, mut results, resp } => {
+                    let start = results.len();
+                    let end = if atomic { keys.len() } else { keys.len().min(start + config.bulk_chunk_size.get()) };
+                    results.reserve_exact(keys.len() - start);
+                    handle_bulk_get(&mut data, &keys[start..end], &mut results, &mut stats);
+                    if end < keys.len() {
+                        command_queue.push(CacheCommand::BulkGet { keys, atomic, results, resp }, None);
+                    } else {
+                        resp.send(Ok(results));
+                    }
+                    "ok"
+                },
+                CacheCommand::ObjectInfo { key, resp } => {
+                    resp.send(Ok(handle_object_info(&mut data, &key)));
+                    "ok"
+                },
+                CacheCommand::Entries { order, limit, resp } => {
+                    resp.send(Ok(handle_entries(&data, order, limit)));
+                    "ok"
+                },
+                CacheCommand::Scan { cursor, options, resp } => {
+                    let page = handle_scan(&data, &mut scan_index, cursor.as_deref(), &options);
+                    if page.cursor.is_none() {
+                        scan_index = None;
+                    }
+                    resp.send(Ok(page));
+                    "ok"
+                },
+                CacheCommand::Snapshot { resp } => {
+                    resp.send(Ok(Snapshot { entries: data.clone(), taken_at: clock::now() }));
+                    "ok"
+                },
+                CacheCommand::Ping { resp } => {
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::Len { resp } => {
+                    resp.send(Ok(data.len()));
+                    "ok"
+                },
+                CacheCommand::DeltaSince { since, resp } => {
+                    resp.send(Ok(handle_delta_since(&data, since)));
+                    "ok"
+                },
+                CacheCommand::MergeDelta { delta, resp } => {
+                    forget_marks(&mut negative, &mut leases, delta.entries.iter().map(|entry| entry.key.as_str()));
+                    invalidate_dependents(&mut data, &mut dependencies, delta.entries.iter().map(|entry| entry.key.as_str()), &mut stats);
+                    let result = handle_merge_delta(&mut data, &mut expiration_queue, &mut interned, &config, delta, &mut stats);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::Flush { in_background, resp } => {
+                    let old_data = std::mem::replace(&mut data, new_entry_map(&config));
+                    let old_expirations = std::mem::take(&mut expiration_queue);
+                    interned.clear();
+                    negative.clear();
+                    leases.clear();
+                    breakers.clear();
+                    sketches.clear();
+                    uniques.clear();
+                    boards.clear();
+                    queues.clear();
+                    dependencies.clear();
+                    if in_background {
+                        thread::spawn(move || drop((old_data, old_expirations)));
+                    }
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::GetStats { resp } => {
+                    stats.deduplicated_bytes = deduplicated_bytes(&interned);
+                    // Include the busy stretch still in progress, which never
+                    // gets folded in while the loop is saturated
+                    let mut current = stats.clone();
+                    current.busy_time += last_wake.elapsed();
+                    let (policies, sizes) = ghosts.split_at(policy_ghosts);
+                    current.policy_simulations = policies.iter().map(|ghost| ghost.simulation().clone()).collect();
+                    current.sizing_curve = sizes.iter().map(|ghost| ghost.simulation().clone()).collect();
+                    resp.send(Ok(current));
+                    "ok"
+                },
+                CacheCommand::SubscribeAlarms { subscriber, resp } => {
+                    alarms.subscribe(subscriber);
+                    resp.send(Ok(()));
+                    "ok"
+                },
+                CacheCommand::UpdateTtl { key, ttl, resp } => {
+                    let result = handle_update_ttl(&mut data, &mut expiration_queue, &key, ttl);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::UpdateTtlRequired { key, ttl, resp } => {
+                    let result = handle_update_ttl_required(&mut data, &mut expiration_queue, &key, ttl);
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::CompareAndSwap { key, expected, new_value, resp } => {
+                    let new_value = intern_value(&mut interned, &config, new_value);
+                    let result = handle_cas(&mut data, &key, &expected, new_value, &mut stats);
+                    if let Ok(true) = result {
+                        leases.remove(&key);
+                        invalidate_dependents(&mut data, &mut dependencies, [key.as_str()], &mut stats);
+                    }
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::IncrByFloat { key, delta, bounds, ttl, resp } => {
+                    let written = has_marks(&negative, &leases, &dependencies).then(|| key.clone());
+                    let now = clock::now();
+                    let created = ttl
+                        .filter(|_| data.get(&key).is_none_or(|entry| entry.expiration.is_some_and(|exp| exp <= now)))
+                        .map(|ttl| (key.clone(), ttl));
+                    let result = handle_incr_by_float(&mut data, &mut expiration_queue, &config, key, delta, bounds, &mut stats);
+                    if let (Ok(_), Some((key, ttl))) = (&result, created) {
+                        let _ = handle_update_ttl(&mut data, &mut expiration_queue, &key, ttl);
+                    }
+                    // A non-numeric value or a bound violation leaves the counter as it was
+                    if let (Ok(_), Some(key)) = (&result, written) {
+                        forget_marks(&mut negative, &mut leases, [key.as_str()]);
+                        invalidate_dependents(&mut data, &mut dependencies, [key.as_str()], &mut stats);
+                    }
+                    let outcome = outcome(&result);
+                    resp.send(result);
+                    outcome
+                },
+                CacheCommand::EvictCorrupted { key, value } => {
+                    if data.get(&key).is_some_and(|entry| entry.value.is_same(&value)) {
+                        data.remove(&key);
+                        stats.corrupted_entries += 1;
+                    }
+                    "ok"
+                },
+                CacheCommand::Shutdown => break 'event_loop,
+            };
+
+            let processing = started.elapsed();
+            stats.queue_wait.record(queue_wait);
+            stats.processing.record(processing);
+
+            let info = CommandInfo { name: command_name, key: hook_key.as_deref() };
+            for middleware in &config.middleware {
+                middleware.after_command(&info, outcome, processing);
+            }
+
+            #[cfg(feature = "tracing")]
+            {
+                span.record("handler_us", processing.as_micros() as u64);
+                span.record("outcome", outcome);
+                // Carries its own fields since the debug-level span is usually filtered out
+                if processing >= config.slow_command_threshold {
+                    tracing::warn!(command = command_name, outcome = outcome, handler_us = processing.as_micros() as u64, "slow command");
+                }
+            }
+        }
+        
+        let now = clock::now();
+        for schedule in schedules.iter_mut().filter(|schedule| schedule.next_run <= now) {
+            let matching: Vec<String> = data.keys()
+                .filter(|key| glob_match(&schedule.pattern, key))
+                .map(|key| key.to_string())
+                .collect();
+            for key in &matching {
+                handle_delete(&mut data, &mut dependencies, &mut negative, &mut leases, key, &mut stats);
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(schedule = schedule.id, pattern = %schedule.pattern, deleted = matching.len(), "ran scheduled delete");
+            // Skip runs missed while the loop was busy rather than bunching them up
+            while schedule.next_run <= now {
+                schedule.next_run += schedule.every;
+            }
+        }
+
+        if expiration_queue.peek().is_some_and(|entry| entry.expiration <= now) {
+            cleanup_expired(&mut data, &mut expiration_queue, &mut dependencies, &mut stats);
+        }
+        // Sweeps that walk whole side tables run once per interval, not on
+        // every expiration
+        if now.duration_since(last_cleanup) >= config.cleanup_interval {
+            // Values only referenced by the intern table are no longer stored anywhere
+            interned.retain(|value| Arc::strong_count(value) > 1);
+            negative.retain(|_, until| *until > now);
+            leases.retain(|_, until| *until > now);
+            breakers.retain(|_, breaker| !breaker.is_idle(now));
+            uniques.values_mut().for_each(|counter| counter.prune(now));
+            boards.values_mut().for_each(|board| board.reset_if_due(now));
+            queues.retain(|_, queue| {
+                queue.requeue_lapsed(now);
+                !queue.is_empty()
+            });
+            // Catches entries evicted, or found expired by a read, since
+            if !dependencies.is_empty() {
+                dependencies.retain(|key| data.contains_key(key));
+            }
+            shrink_if_sparse(&mut data, &mut expiration_queue, &config);
+            // Let go of the key index of a scan nobody is continuing
+            if scan_index.as_ref().is_some_and(|index| now.duration_since(index.last_used) >= config.cleanup_interval) {
+                scan_index = None;
+            }
+            last_cleanup = now;
+        }
+        if now >= alarms.next_check() {
+            alarms.check(&config.alarms, &data, &stats, now);
+        }
+
+        // Block until a command arrives or the earliest expiration is due
+        if command_queue.is_empty() {
+            let mut wake_at = last_cleanup + config.cleanup_interval;
+            if let Some(entry) = expiration_queue.peek() {
+                wake_at = wake_at.min(entry.expiration);
+            }
+            if let Some(next_run) = schedules.iter().map(|schedule| schedule.next_run).min() {
+                wake_at = wake_at.min(next_run);
+            }
+            if !config.alarms.is_empty() {
+                wake_at = wake_at.min(alarms.next_check());
+            }
+
+            let idle_from = Instant::now();
+            stats.busy_time += idle_from.duration_since(last_wake);
+            match receiver.recv_timeout(wake_at.saturating_duration_since(clock::now())) {
+                Ok(Envelope { cmd: CacheCommand::Shutdown, .. }) | Err(RecvTimeoutError::Disconnected) => break 'event_loop,
+                Ok(Envelope { cmd, deadline }) => command_queue.push(admit(cmd, &config, &mut ghosts, &mut stats), deadline),
+                Err(RecvTimeoutError::Timeout) => {},
+            }
+            last_wake = Instant::now();
+            stats.idle_time += last_wake.duration_since(idle_from);
+            stats.loop_wakeups += 1;
+        }
+    }
+
+    // Whatever is still queued is answered rather than dropped, so blocked
+    // callers return and callbacks still run. Dropping the receiver then makes
+    // later sends fail at once; only a command sent between the last drain
+    // and that drop goes unanswered, and its channel disconnects instead.
+    reject_pending(&receiver, &mut command_queue);
+    drop(receiver);
+
+    #[cfg(feature = "persistence")]
+    if let Some(path) = &config.persistence_path {
+        let result = persistence::save_snapshot(path, &data);
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(()) => tracing::info!(path = %path.display(), entries = data.len(), "saved snapshot"),
+            Err(e) => tracing::error!(path = %path.display(), error = %e, "failed to save snapshot"),
+        }
+        result?;
+    }
+    Ok(())
+}
+
+fn reject_pending(receiver: &Receiver<Envelope>, command_queue: &mut CommandQueue) {
+    while let Some(Queued { cmd, .. }) = command_queue.pop() {
+        cmd.reject(CacheError::LockError);
+    }
+    for Envelope { cmd, .. } in receiver.try_iter() {
+        cmd.reject(CacheError::LockError);
+    }
+}
+
+// Identical values share one allocation when deduplication is enabled; values
+// small enough to be stored inline gain nothing from sharing.
+fn intern_value(interned: &mut HashSet<Arc<str>>, config: &CacheConfig, value: String) -> StoredValue {
+    if !config.deduplicate_values || value.len() <= INLINE_CAPACITY {
+        return value.into();
+    }
+    if let Some(shared) = interned.get(value.as_str()) {
+        return shared.clone().into();
+    }
+
+    let value: Arc<str> = value.into();
+    interned.insert(value.clone());
+    value.into()
+}
+
+fn deduplicated_bytes(interned: &HashSet<Arc<str>>) -> u64 {
+    interned.iter()
+        .map(|value| (Arc::strong_count(value).saturating_sub(2) * value.len()) as u64)
+        .sum()
+}
+
+// Applied once as a command arrives, so requeued bulk chunks aren't
+// normalized or replayed to the simulations twice
+fn admit(mut cmd: CacheCommand, config: &CacheConfig, ghosts: &mut [GhostCache], stats: &mut CacheStats) -> CacheCommand {
+    if let Some(normalize) = &config.key_normalizer {
+        cmd.normalize_keys(normalize.as_ref(), stats);
+    }
+    if !ghosts.is_empty() {
+        replay_to_ghosts(ghosts, &cmd);
+    }
+    cmd
+}
+
+// Returns the simulated caches along with how many of them, from the front,
+// compare policies; the rest are LRU caches larger than max_size
+fn new_ghosts(config: &CacheConfig) -> (Vec<GhostCache>, usize) {
+    let Some(max_size) = config.max_size else {
+        return (Vec::new(), 0);
+    };
+    let mut ghosts = Vec::new();
+    if config.simulate_policies {
+        for policy in [SimulatedPolicy::Lru, SimulatedPolicy::Lfu, SimulatedPolicy::Fifo] {
+            ghosts.push(GhostCache::new(policy, max_size.get()));
+        }
+    }
+    let policy_ghosts = ghosts.len();
+    if config.sizing_curve {
+        for growth in SIZING_CURVE_GROWTH {
+            let capacity = (max_size.get() as f64 * growth).ceil() as usize;
+            ghosts.push(GhostCache::new(SimulatedPolicy::Lru, capacity));
+        }
+    }
+    (ghosts, policy_ghosts)
+}
+
+// What new_ghosts sizes the simulations from. When a reload or set_max_size
+// changes any of it, the simulations are rebuilt and start over cold, so
+// they never compare against a capacity the cache no longer has.
+fn simulated_settings(config: &CacheConfig) -> (Option<NonZeroUsize>, bool, bool) {
+    (config.max_size, config.simulate_policies, config.sizing_curve)
+}
+
+// Reads and writes as a plain cache-aside client would see them: a read
+// only counts a hit or miss, and it is the following write that inserts
+fn replay_to_ghosts(ghosts: &mut [GhostCache], cmd: &CacheCommand) {
+    for ghost in ghosts {
+        match cmd {
+            CacheCommand::Get { key, .. }
+            | CacheCommand::Lookup { key, .. }
+            | CacheCommand::GetEx { key, .. }
+            | CacheCommand::GetRequired { key, .. } => ghost.read(key),
+            CacheCommand::Checkout { key, .. } => ghost.read(key),
+            CacheCommand::BulkGet { keys, .. } => keys.iter().for_each(|key| ghost.read(key)),
+            CacheCommand::CheckAndRecord { key, .. } => {
+                ghost.read(key);
+                ghost.write(key);
+            },
+            CacheCommand::Set { key, .. }
+            | CacheCommand::SetWithDependencies { key, .. }
+            | CacheCommand::CompareAndSwap { key, .. }
+            | CacheCommand::IncrByFloat { key, .. } => ghost.write(key),
+            CacheCommand::BulkSet { items, .. } => items.iter().for_each(|(key, _)| ghost.write(key)),
+            CacheCommand::BulkSetNx { items, .. } => items.iter().for_each(|(key, _)| ghost.write(key)),
+            CacheCommand::MergeDelta { delta, .. } => delta.entries.iter().for_each(|entry| ghost.write(&entry.key)),
+            CacheCommand::Delete { key, .. } | CacheCommand::SetNegative { key, .. } => ghost.remove(key),
+            CacheCommand::Flush { .. } => ghost.clear(),
+            _ => {},
+        }
+    }
+}
+
+// A board as of now, emptied first if a reset period has passed
+fn current_board<'a>(boards: &'a mut HashMap<String, Board>, key: &str) -> Option<&'a mut Board> {
+    let board = boards.get_mut(key)?;
+    board.reset_if_due(clock::now());
+    Some(board)
+}
+
+fn before_command(config: &CacheConfig, name: &'static str, cmd: &CacheCommand) -> Option<CacheError> {
+    let info = CommandInfo { name, key: cmd.key() };
+    config.middleware.iter().find_map(|middleware| middleware.before_command(&info).err())
+}
+
+fn outcome<T>(result: &Result<T, CacheError>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(_) => "error",
+    }
+}
+
+fn lookup_outcome(result: &Result<Option<String>, CacheError>) -> &'static str {
+    match result {
+        Ok(Some(_)) => "hit",
+        Ok(None) => "miss",
+        Err(_) => "error",
+    }
+}
+
+fn handle_set(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    config: &CacheConfig,
+    key: String,
+    value: StoredValue,
+    options: SetOptions,
+    stats: &mut CacheStats,
+) -> Result<(), CacheError> {
+    // Before this key's own deadline is queued, which may already be due
+    if let Some(max_size) = config.max_size {
+        if data.len() >= max_size.get() && !data.contains_key(&key) {
+            expire_due(data, expiration_queue, stats);
+            if data.len() >= max_size.get() {
+                evict_entries(data, 1, stats)?;
+            }
+        }
+    }
+    store_entry(data, expiration_queue, config, key, value, options);
+    Ok(())
+}
+
+// Writes the entry without making room for it; callers enforce max_size
+fn store_entry(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    config: &CacheConfig,
+    key: String,
+    value: StoredValue,
+    options: SetOptions,
+) {
+    let now = clock::now();
+    let kept_expiration = match options.ttl {
+        None if options.keep_ttl.unwrap_or(config.keep_ttl) => data.get(&key)
+            .filter(|entry| entry.expiration.is_none_or(|exp| exp > now))
+            .map(|entry| entry.expiration),
+        _ => None,
+    };
+
+    let expiration = match kept_expiration {
+        Some(expiration) => expiration,
+        None => options.ttl.or(config.default_ttl).map(|duration| now + duration),
+    };
+
+    if let (Some(exp), None) = (expiration, kept_expiration) {
+        expiration_queue.push(ExpirationEntry {
+            expiration: exp,
+            key: key.clone(),
+        });
+    }
+
+    data.insert(key, CacheEntry {
+        expiration,
+        inserted_at: now,
+        last_accessed: now,
+        access_count: 0,
+        written_at: delta::wall_clock_micros(),
+        checksum: config.checksums.then(|| value.checksum()),
+        value,
+    });
+}
+
+fn handle_delta_since(data: &EntryMap, since: u64) -> Delta {
+    let now = clock::now();
+    // Taken before scanning so a write landing in the same microsecond is
+    // sent again next time rather than skipped
+    let watermark = delta::wall_clock_micros();
+    let entries = data.iter()
+        .filter(|(_, entry)| entry.written_at >= since && entry.expiration.is_none_or(|exp| exp > now))
+        .map(|(key, entry)| DeltaEntry {
+            key: key.clone(),
+            value: entry.value.to_string(),
+            ttl: entry.expiration.map(|exp| exp.duration_since(now)),
+            written_at: entry.written_at,
+        })
+        .collect();
+    Delta { entries, watermark }
+}
+
+fn handle_merge_delta(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    interned: &mut HashSet<Arc<str>>,
+    config: &CacheConfig,
+    delta: Delta,
+    stats: &mut CacheStats,
+) -> Result<usize, CacheError> {
+    let now = clock::now();
+    let mut applied = 0;
+
+    for incoming in delta.entries {
+        let wins = match data.get(&incoming.key) {
+            Some(local) if local.expiration.is_none_or(|exp| exp > now) => {
+                (incoming.written_at, incoming.value.as_str()) > (local.written_at, local.value.as_str())
+            },
+            _ => true,
+        };
+        if !wins {
+            continue;
+        }
+
+        let expiration = incoming.ttl.map(|ttl| now + ttl);
+        if let Some(exp) = expiration {
+            expiration_queue.push(ExpirationEntry {
+                expiration: exp,
+                key: incoming.key.clone(),
+            });
+        }
+        let value = intern_value(interned, config, incoming.value);
+        data.insert(incoming.key, CacheEntry {
+            checksum: config.checksums.then(|| value.checksum()),
+            value,
+            expiration,
+            inserted_at: now,
+            last_accessed: now,
+            access_count: 0,
+            written_at: incoming.written_at,
+        });
+        applied += 1;
+    }
+
+    enforce_max_size(data, expiration_queue, config, stats)?;
+    Ok(applied)
+}
+
+fn handle_bulk_set(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    interned: &mut HashSet<Arc<str>>,
+    config: &CacheConfig,
+    items: Vec<(String, String)>,
+    stats: &mut CacheStats,
+) -> Result<(), CacheError> {
+    insert_batch(data, expiration_queue, interned, config, items, stats)
+}
+
+// Inserts every item without per-key eviction, then trims back to max_size in
+// one pass. Items evicted by the trim are the least recently used, exactly as
+// if they had been inserted one at a time.
+fn insert_batch(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    interned: &mut HashSet<Arc<str>>,
+    config: &CacheConfig,
+    items: Vec<(String, String)>,
+    stats: &mut CacheStats,
+) -> Result<(), CacheError> {
+    // Room is made once for the whole batch rather than per item
+    for (key, value) in items {
+        let value = intern_value(interned, config, value);
+        store_entry(data, expiration_queue, config, key, value, SetOptions::default());
+    }
+    enforce_max_size(data, expiration_queue, config, stats)
+}
+
+fn handle_bulk_set_nx(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    interned: &mut HashSet<Arc<str>>,
+    config: &CacheConfig,
+    items: Vec<(String, String)>,
+    stats: &mut CacheStats,
+) -> Result<bool, CacheError> {
+    for (key, _) in &items {
+        remove_if_expired(data, key);
+        if data.contains_key(key) {
+            return Ok(false);
+        }
+    }
+
+    insert_batch(data, expiration_queue, interned, config, items, stats)?;
+    Ok(true)
+}
+
+fn handle_get(
+    data: &mut EntryMap,
+    key: &str,
+    stats: &mut CacheStats,
+) -> Result<Option<String>, CacheError> {
+    if let Some(entry) = data.get_mut(key) {
+        if let Some(exp) = entry.expiration {
+            if clock::now() > exp {
+                data.remove(key);
+                stats.record_miss(key);
+                return Ok(None);
+            }
+        }
+        if !entry.is_intact() {
+            data.remove(key);
+            stats.corrupted_entries += 1;
+            return Err(CacheError::CorruptedEntry(key.to_string()));
         }
+        
+        entry.last_accessed = clock::now();
+        entry.access_count += 1;
+        stats.record_hit(key);
+        Ok(Some(entry.value.to_string()))
+    } else {
+        stats.record_miss(key);
+        Ok(None)
     }
+}
 
-    pub fn set(&self, key: String, value: String, ttl: Option<Duration>) -> Result<(), CacheError> {
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::Set { key, value, ttl, resp: resp_sender })
-            .map_err(|_| CacheError::LockError)?;
-        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+// A live entry big enough to finish on a value worker: counts the hit and
+// hands back the value along with the checksum it should still match
+fn large_hit(data: &mut EntryMap, key: &str, stats: &mut CacheStats) -> Option<(StoredValue, Option<u32>)> {
+    if remove_if_expired(data, key) {
+        return None;
     }
+    let entry = data.get_mut(key).filter(|entry| entry.value.len() >= OFFLOAD_MIN_BYTES)?;
+    entry.last_accessed = clock::now();
+    entry.access_count += 1;
+    stats.record_hit(key);
+    Some((entry.value.clone(), entry.checksum))
+}
 
-    pub fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::Get { 
-            key: key.to_string(), 
-            resp: resp_sender,
-        })
-        .map_err(|_| CacheError::LockError)?;
-        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+fn remove_if_expired(data: &mut EntryMap, key: &str) -> bool {
+    let expired = data.get(key)
+        .and_then(|entry| entry.expiration)
+        .is_some_and(|exp| clock::now() > exp);
+    if expired {
+        data.remove(key);
     }
+    expired
+}
 
-    pub fn bulk_set<I>(&self, items: I) -> Result<(), CacheError>
-    where
-        I: IntoIterator<Item = (String, String)>,
-    {
-        let items_vec: Vec<_> = items.into_iter().collect();
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::BulkSet { 
-            items: items_vec, 
-            resp: resp_sender,
-        })
-        .map_err(|_| CacheError::LockError)?;
-        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+fn handle_lookup(
+    data: &mut EntryMap,
+    negative: &mut HashMap<String, Instant>,
+    key: &str,
+    stats: &mut CacheStats,
+) -> Result<Lookup, CacheError> {
+    let now = clock::now();
+    let live = data.get(key).is_some_and(|entry| entry.expiration.is_none_or(|exp| now <= exp));
+    if !live {
+        match negative.get(key) {
+            Some(until) if *until > now => {
+                stats.negative_hits += 1;
+                return Ok(Lookup::NotFoundCached);
+            },
+            Some(_) => {
+                negative.remove(key);
+            },
+            None => {},
+        }
     }
 
-    pub fn bulk_get<I, T>(&self, keys: I) -> Result<HashMap<T, Option<String>>, CacheError>
-    where
-        I: IntoIterator<Item = T>,
-        T: Into<String> + Clone + std::hash::Hash + Eq,
-    {
-        let keys_vec: Vec<T> = keys.into_iter().collect();
-        let keys_string: Vec<String> = keys_vec.iter().map(|k| k.clone().into()).collect();
-        
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::BulkGet { 
-            keys: keys_string, 
-            resp: resp_sender,
-        })
-        .map_err(|_| CacheError::LockError)?;
-        
-        let result = resp_receiver.recv().map_err(|_| CacheError::LockError)?;
-        
-        
-        let mut converted_result = HashMap::new();
-        if let Ok(string_result) = result {
-            for key in keys_vec { // / Convert the result back to the original key type
-                let string_key = key.clone().into();
-                converted_result.insert(key, string_result.get(&string_key).cloned().flatten());
+    Ok(match handle_get(data, key, stats)? {
+        Some(value) => Lookup::Hit(value),
+        None => Lookup::Miss,
+    })
+}
+
+// A negative entry replaces any value. Negative entries get their own
+// max_size-sized budget, so a flood of lookups for made-up IDs can't grow
+// memory without bound.
+fn handle_set_negative(
+    data: &mut EntryMap,
+    negative: &mut HashMap<String, Instant>,
+    config: &CacheConfig,
+    key: String,
+    ttl: Option<Duration>,
+) {
+    let now = clock::now();
+    data.remove(key.as_str());
+    if let Some(max_size) = config.max_size {
+        if negative.len() >= max_size.get() && !negative.contains_key(&key) {
+            negative.retain(|_, until| *until > now);
+            if negative.len() >= max_size.get() {
+                if let Some(soonest) = negative.iter().min_by_key(|(_, until)| **until).map(|(key, _)| key.clone()) {
+                    negative.remove(&soonest);
+                }
             }
         }
-        
-        Ok(converted_result)
     }
+    negative.insert(key, now + ttl.unwrap_or(config.negative_ttl));
+}
 
-    pub fn get_stats(&self) -> Result<CacheStats, CacheError> {
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::GetStats { 
-            resp: resp_sender,
-        })
-        .map_err(|_| CacheError::LockError)?;
-        resp_receiver.recv().map_err(|_| CacheError::LockError)?
-    }
+// Whether a write has anything for forget_marks or invalidate_dependents to
+// settle, so the key is only copied out when it does
+fn has_marks(negative: &HashMap<String, Instant>, leases: &HashMap<String, Instant>, dependencies: &DependencyGraph) -> bool {
+    !negative.is_empty() || !leases.is_empty() || !dependencies.is_empty()
+}
 
-    pub fn update_ttl(&self, key: &str, ttl: Duration) -> Result<bool, CacheError> {
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::UpdateTtl { 
-            key: key.to_string(), 
-            ttl, 
-            resp: resp_sender,
-        })
-        .map_err(|_| CacheError::LockError)?;
-        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+// A write settles both a cached "not found" and any lease held on the key
+fn forget_marks<'a>(
+    negative: &mut HashMap<String, Instant>,
+    leases: &mut HashMap<String, Instant>,
+    keys: impl IntoIterator<Item = &'a str>,
+) {
+    if negative.is_empty() && leases.is_empty() {
+        return;
     }
-
-    pub fn compare_and_swap(&self, key: &str, expected: &str, new_value: String) -> Result<bool, CacheError> {
-        let (resp_sender, resp_receiver) = mpsc::channel();
-        self.sender.send(CacheCommand::CompareAndSwap { 
-            key: key.to_string(), 
-            expected: expected.to_string(), 
-            new_value, 
-            resp: resp_sender,
-        })
-        .map_err(|_| CacheError::LockError)?;
-        resp_receiver.recv().map_err(|_| CacheError::LockError)?
+    for key in keys {
+        negative.remove(key);
+        leases.remove(key);
     }
 }
 
-impl Drop for Cache {
-    fn drop(&mut self) {
-        self.running.store(false, Ordering::Relaxed);
-        let _ = self.sender.send(CacheCommand::Shutdown);
-        
-        if let Some(handle) = Arc::get_mut(&mut self.event_loop_handle).and_then(|opt| opt.take()) {
-            let _ = handle.join();
-        }
+fn handle_try_lease(leases: &mut HashMap<String, Instant>, key: String, ttl: Duration) -> bool {
+    let now = clock::now();
+    if leases.get(&key).is_some_and(|until| *until > now) {
+        return false;
     }
+    leases.insert(key, now + ttl);
+    true
 }
 
-fn run_event_loop(receiver: Receiver<CacheCommand>, config: CacheConfig, running: Arc<AtomicBool>)-> Result<(), Box<dyn std::error::Error>>{
-    let mut data = HashMap::new();
-    let mut expiration_queue = BinaryHeap::new();
-    let mut stats = CacheStats::default();
-    let mut command_queue = VecDeque::new();
-    
-    let mut last_cleanup = Instant::now();
-    
-    while running.load(Ordering::Relaxed) {
-        // Process any pending commands
-        while let Ok(cmd) = receiver.try_recv() {
-            match cmd {
-                CacheCommand::Shutdown => return Ok(()),
-                cmd => command_queue.push_back(cmd),
-            }
-        }
-        
-        // Process one command from the queue
-        if let Some(cmd) = command_queue.pop_front() {
-            match cmd {
-                CacheCommand::Set { key, value, ttl, resp } => {
-                    let result = handle_set(&mut data, &mut expiration_queue, &config, key, value, ttl, &mut stats);
-                    let _ = resp.send(result);
-                },
-                CacheCommand::Get { key, resp } => {
-                    let result = handle_get(&mut data, &key, &mut stats);
-                    let _ = resp.send(result);
-                },
-                CacheCommand::BulkSet { items, resp } => {
-                    let mut result = Ok(());
-                    for (key, value) in items {
-                        if let Err(e) = handle_set(&mut data, &mut expiration_queue, &config, key, value, None, &mut stats) {
-                            result = Err(e);
-                            break;
-                        }
-                    }
-                    let _ = resp.send(result);
-                },
-                CacheCommand::BulkGet { keys, resp } => {
-                    let mut results = HashMap::new();
-                    for key in keys {
-                        results.insert(key.clone(), handle_get(&mut data, &key, &mut stats)?);
-                    }
-                    let _ = resp.send(Ok(results));
-                },
-                CacheCommand::GetStats { resp } => {
-                    let _ = resp.send(Ok(stats.clone()));
-                },
-                CacheCommand::UpdateTtl { key, ttl, resp } => {
-                    let result = handle_update_ttl(&mut data, &mut expiration_queue, &key, ttl);
-                    let _ = resp.send(result);
-                },
-                CacheCommand::CompareAndSwap { key, expected, new_value, resp } => {
-                    let result = handle_cas(&mut data, &key, &expected, new_value);
-                    let _ = resp.send(result);
-                },
-                CacheCommand::Shutdown => return Ok(()),
+// Only explicit writes and deletes cascade: a dependency that expires or is
+// evicted leaves its dependents in place until their own TTL runs out. The
+// written keys' own edges go too, since the value they were derived for is gone.
+fn invalidate_dependents<'a>(
+    data: &mut EntryMap,
+    dependencies: &mut DependencyGraph,
+    keys: impl IntoIterator<Item = &'a str>,
+    stats: &mut CacheStats,
+) {
+    if dependencies.is_empty() {
+        return;
+    }
+    for key in keys {
+        for dependent in dependencies.take_dependents(key) {
+            if data.remove(dependent.as_str()).is_some() {
+                stats.dependency_invalidations += 1;
             }
-    
-        }
-        
-        let now = Instant::now();
-        if now.duration_since(last_cleanup) >= config.cleanup_interval {
-            cleanup_expired(&mut data, &mut expiration_queue, &mut stats);
-            last_cleanup = now;
         }
-        
-        // small sleep to prevent busy-waiting
-        thread::sleep(Duration::from_millis(1));   
+        dependencies.forget(key);
     }
-    Ok(())
 }
 
-fn handle_set(
-    data: &mut HashMap<String, CacheEntry>,
+fn handle_delete(
+    data: &mut EntryMap,
+    dependencies: &mut DependencyGraph,
+    negative: &mut HashMap<String, Instant>,
+    leases: &mut HashMap<String, Instant>,
+    key: &str,
+    stats: &mut CacheStats,
+) -> bool {
+    forget_marks(negative, leases, [key]);
+    invalidate_dependents(data, dependencies, [key], stats);
+    // An expired entry cleanup has not reached yet is already gone as far as
+    // callers can tell
+    !remove_if_expired(data, key) && data.remove(key).is_some()
+}
+
+fn handle_checkout(
+    data: &mut EntryMap,
+    leases: &mut HashMap<String, Instant>,
+    key: String,
+    lease_ttl: Duration,
+    stats: &mut CacheStats,
+) -> Result<Option<String>, CacheError> {
+    let now = clock::now();
+    if leases.get(&key).is_some_and(|until| *until > now) {
+        return Ok(None);
+    }
+    let value = handle_get(data, &key, stats)?;
+    if value.is_some() {
+        leases.insert(key, now + lease_ttl);
+    }
+    Ok(value)
+}
+
+fn handle_check_and_record(
+    data: &mut EntryMap,
     expiration_queue: &mut BinaryHeap<ExpirationEntry>,
     config: &CacheConfig,
-    key: String,
-    value: String,
+    key: &str,
+    value: StoredValue,
     ttl: Option<Duration>,
     stats: &mut CacheStats,
-) -> Result<(), CacheError> {
-    let expiration = ttl.or(config.default_ttl)
-        .map(|duration| Instant::now() + duration);
-
-    if let Some(exp) = expiration {
-        expiration_queue.push(ExpirationEntry {
-            expiration: exp,
-            key: key.clone(),
-        });
+) -> Result<Option<String>, CacheError> {
+    if let Some(recorded) = handle_get(data, key, stats)? {
+        return Ok(Some(recorded));
     }
+    let options = SetOptions { ttl, keep_ttl: None };
+    handle_set(data, expiration_queue, config, key.to_string(), value, options, stats)?;
+    Ok(None)
+}
 
-    if let Some(max_size) = config.max_size {
-        if data.len() >= max_size.get() && !data.contains_key(&key) {
-            evict_entry(data, stats)?;
-        }
+fn handle_get_required(
+    data: &mut EntryMap,
+    key: &str,
+    stats: &mut CacheStats,
+) -> Result<String, CacheError> {
+    if remove_if_expired(data, key) {
+        stats.record_miss(key);
+        return Err(CacheError::KeyExpired);
     }
-
-    data.insert(key, CacheEntry {
-        value,
-        expiration,
-        last_accessed: Instant::now(),
-        access_count: 0,
-    });
-
-    Ok(())
+    handle_get(data, key, stats)?.ok_or(CacheError::KeyNotFound)
 }
 
-fn handle_get(
-    data: &mut HashMap<String, CacheEntry>,
+fn handle_get_ex(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
     key: &str,
+    expiry: Expiry,
     stats: &mut CacheStats,
 ) -> Result<Option<String>, CacheError> {
-    if let Some(entry) = data.get_mut(key) {
-        if let Some(exp) = entry.expiration {
-            if Instant::now() > exp {
-                data.remove(key);
-                stats.misses += 1;
-                return Ok(None);
-            }
+    let value = handle_get(data, key, stats)?;
+    if value.is_some() {
+        match expiry {
+            Expiry::After(ttl) => {
+                handle_update_ttl(data, expiration_queue, key, ttl)?;
+            },
+            Expiry::Persist => {
+                if let Some(entry) = data.get_mut(key) {
+                    entry.expiration = None;
+                }
+            },
         }
-        
-        entry.last_accessed = Instant::now();
-        entry.access_count += 1;
-        stats.hits += 1;
-        Ok(Some(entry.value.clone()))
-    } else {
-        stats.misses += 1;
-        Ok(None)
     }
+    Ok(value)
+}
+
+// One lookup pass with a single clock read; expired and corrupted keys are
+// removed and the hit/miss counters updated once at the end. A corrupted entry
+// reads as a miss here rather than failing the whole batch.
+fn handle_bulk_get(
+    data: &mut EntryMap,
+    keys: &[String],
+    results: &mut Vec<Option<String>>,
+    stats: &mut CacheStats,
+) {
+    let now = clock::now();
+    let mut stale_keys = Vec::new();
+
+    for key in keys {
+        match data.get_mut(key.as_str()) {
+            Some(entry) if entry.expiration.is_some_and(|exp| now > exp) => {
+                stats.record_miss(key);
+                stale_keys.push(key.as_str());
+                results.push(None);
+            },
+            Some(entry) if !entry.is_intact() => {
+                stats.corrupted_entries += 1;
+                stats.record_miss(key);
+                stale_keys.push(key.as_str());
+                results.push(None);
+            },
+            Some(entry) => {
+                entry.last_accessed = now;
+                entry.access_count += 1;
+                stats.record_hit(key);
+                results.push(Some(entry.value.to_string()));
+            },
+            None => {
+                stats.record_miss(key);
+                results.push(None);
+            },
+        }
+    }
+
+    for key in stale_keys {
+        data.remove(key);
+    }
+}
+
+fn take_chunk<T>(items: &mut VecDeque<T>, size: usize) -> Vec<T> {
+    if items.len() <= size {
+        return Vec::from(std::mem::take(items));
+    }
+    items.drain(..size).collect()
 }
 
 fn handle_update_ttl(
-    data: &mut HashMap<String, CacheEntry>,
+    data: &mut EntryMap,
     expiration_queue: &mut BinaryHeap<ExpirationEntry>,
     key: &str,
     ttl: Duration,
 ) -> Result<bool, CacheError> {
+    if remove_if_expired(data, key) {
+        return Ok(false);
+    }
     if let Some(entry) = data.get_mut(key) {
-        let new_expiration = Instant::now() + ttl;
+        let new_expiration = clock::now() + ttl;
         entry.expiration = Some(new_expiration);
         
         expiration_queue.push(ExpirationEntry {
@@ -302,15 +2508,40 @@ fn handle_update_ttl(
     }
 }
 
+fn handle_update_ttl_required(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    key: &str,
+    ttl: Duration,
+) -> Result<(), CacheError> {
+    if remove_if_expired(data, key) {
+        return Err(CacheError::KeyExpired);
+    }
+    if handle_update_ttl(data, expiration_queue, key, ttl)? {
+        Ok(())
+    } else {
+        Err(CacheError::KeyNotFound)
+    }
+}
+
 fn handle_cas(
-    data: &mut HashMap<String, CacheEntry>,
+    data: &mut EntryMap,
     key: &str,
     expected: &str,
-    new_value: String,
+    new_value: StoredValue,
+    stats: &mut CacheStats,
 ) -> Result<bool, CacheError> {
+    if remove_if_expired(data, key) {
+        return Ok(false);
+    }
     if let Some(entry) = data.get_mut(key) {
-        if entry.value == expected {
-            entry.value = new_value;
+        if !entry.is_intact() {
+            data.remove(key);
+            stats.corrupted_entries += 1;
+            return Err(CacheError::CorruptedEntry(key.to_string()));
+        }
+        if *entry.value == *expected {
+            entry.set_value(new_value);
             Ok(true)
         } else {
             Ok(false)
@@ -320,39 +2551,313 @@ fn handle_cas(
     }
 }
 
+// Introspection only: unlike get, this does not count as an access
+fn handle_object_info(data: &mut EntryMap, key: &str) -> Option<ObjectInfo> {
+    if remove_if_expired(data, key) {
+        return None;
+    }
+
+    let now = clock::now();
+    data.get(key).map(|entry| ObjectInfo {
+        idle_time: now.duration_since(entry.last_accessed),
+        frequency: entry.access_count,
+        age: now.duration_since(entry.inserted_at),
+        ttl: entry.expiration.map(|exp| exp.saturating_duration_since(now)),
+    })
+}
+
+fn handle_entries(
+    data: &EntryMap,
+    order: EntryOrder,
+    limit: usize,
+) -> Vec<(String, String)> {
+    let now = clock::now();
+    let mut live: Vec<_> = data.iter()
+        .filter(|(_, entry)| entry.expiration.is_none_or(|exp| exp > now))
+        .collect();
+
+    // Oldest first; entries without a TTL sort after every expiring entry
+    let sort_key = |entry: &CacheEntry| match order {
+        EntryOrder::Insertion => (false, entry.inserted_at),
+        EntryOrder::LastAccess => (false, entry.last_accessed),
+        EntryOrder::Expiration => entry.expiration.map_or((true, entry.inserted_at), |exp| (false, exp)),
+    };
+
+    if limit < live.len() {
+        if limit == 0 {
+            return Vec::new();
+        }
+        live.select_nth_unstable_by_key(limit - 1, |(_, entry)| sort_key(entry));
+        live.truncate(limit);
+    }
+    live.sort_by_key(|(_, entry)| sort_key(entry));
+
+    live.into_iter()
+        .map(|(key, entry)| (key.clone(), entry.value.to_string()))
+        .collect()
+}
+
+// Keys are visited in lexicographic order after the cursor, so every key that
+// exists for the whole scan is returned exactly once, even across rehashes.
+fn handle_scan(
+    data: &EntryMap,
+    index: &mut Option<ScanIndex>,
+    cursor: Option<&str>,
+    options: &ScanOptions,
+) -> ScanPage {
+    let now = clock::now();
+    // A new scan starts from a fresh index; one that has been dropped in the
+    // middle of a scan is rebuilt, which still holds every key live since
+    let index = match index {
+        Some(index) if cursor.is_some() => index,
+        _ => index.insert(ScanIndex::build(data)),
+    };
+    index.last_used = now;
+
+    let start = cursor.map_or(0, |cursor| index.keys.partition_point(|key| key.as_str() <= cursor));
+    let mut examined = Vec::with_capacity(options.count);
+    let mut next = start;
+    while next < index.keys.len() && examined.len() < options.count {
+        let key = &index.keys[next];
+        if data.get(key).is_some_and(|entry| entry.expiration.is_none_or(|exp| exp > now)) {
+            examined.push(key);
+        }
+        next += 1;
+    }
+
+    let cursor = if next < index.keys.len() {
+        examined.last().map(|key| key.to_string())
+    } else {
+        None
+    };
+    let keys = examined.into_iter()
+        .filter(|key| options.pattern.as_deref().is_none_or(|pattern| glob_match(pattern, key)))
+        .cloned()
+        .collect();
+
+    ScanPage { keys, cursor }
+}
+
+// Every key in order as of the start of a scan, so each page is a binary
+// search plus `count` steps instead of a pass over the whole map. Keys written
+// after it was built may be missed, as with Redis SCAN; removed ones are
+// skipped.
+struct ScanIndex {
+    keys: Vec<String>,
+    last_used: Instant,
+}
+
+impl ScanIndex {
+    fn build(data: &EntryMap) -> Self {
+        let mut keys: Vec<String> = data.keys().cloned().collect();
+        keys.sort_unstable();
+        ScanIndex { keys, last_used: clock::now() }
+    }
+}
+
+// Existing keys keep their TTL; new keys are created through handle_set so
+// they pick up the default TTL and respect max_size.
+fn handle_incr_by_float(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    config: &CacheConfig,
+    key: String,
+    delta: f64,
+    bounds: Option<CounterBounds>,
+    stats: &mut CacheStats,
+) -> Result<f64, CacheError> {
+    remove_if_expired(data, &key);
+    if data.get(&key).is_some_and(|entry| !entry.is_intact()) {
+        data.remove(&key);
+        stats.corrupted_entries += 1;
+        return Err(CacheError::CorruptedEntry(key));
+    }
+
+    let current = match data.get(&key) {
+        Some(entry) => entry.value.trim().parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| CacheError::ValueNotNumeric(entry.value.to_string()))?,
+        None => 0.0,
+    };
+
+    let mut updated = current + delta;
+    if !updated.is_finite() {
+        return Err(CacheError::ValueNotNumeric(updated.to_string()));
+    }
+    if let Some(bounds) = bounds {
+        if updated < bounds.min || updated > bounds.max {
+            match bounds.overflow {
+                Overflow::Clamp => updated = updated.clamp(bounds.min, bounds.max),
+                Overflow::Error => return Err(CacheError::CounterOutOfRange),
+            }
+        }
+    }
+
+    match data.get_mut(&key) {
+        Some(entry) => entry.set_value(updated.to_string().into()),
+        None => handle_set(data, expiration_queue, config, key, updated.to_string().into(), SetOptions::default(), stats)?,
+    }
+    Ok(updated)
+}
+
+// Runs whenever the earliest deadline in the queue has passed, so each key goes
+// at its own deadline. That is why there is no Redis-style random sampling of
+// volatile keys between cleanups: it would never find anything left to expire.
 fn cleanup_expired(
-    data: &mut HashMap<String, CacheEntry>,
+    data: &mut EntryMap,
     expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    dependencies: &mut DependencyGraph,
     stats: &mut CacheStats,
 ) {
-    let now = Instant::now();
+    for key in expire_due(data, expiration_queue, stats) {
+        dependencies.forget(&key);
+    }
+}
+
+// Removes every entry whose deadline has passed and returns their keys. Also
+// run before evicting, so a key that expired while the loop was busy with
+// commands gives up its max_size slot instead of a live entry losing one; the
+// dependency edges of those keys go on the next cleanup tick.
+fn expire_due(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    stats: &mut CacheStats,
+) -> Vec<String> {
+    let now = clock::now();
     let mut expired_keys = Vec::new();
 
     while let Some(entry) = expiration_queue.peek() {
         if entry.expiration > now {
             break;
         }
-        
-        expired_keys.push(entry.key.clone());
-        expiration_queue.pop();
+        let Some(entry) = expiration_queue.pop() else {
+            break;
+        };
+
+        // The queue may hold stale deadlines for keys whose TTL was since extended or removed
+        let expired = data.get(&entry.key)
+            .and_then(|entry| entry.expiration)
+            .is_some_and(|exp| exp <= now);
+        if expired {
+            data.remove(&entry.key);
+            stats.record_eviction(&entry.key);
+            expired_keys.push(entry.key);
+        }
+    }
+    expired_keys
+}
+
+// Applies everything except the settings fixed at startup, which keep their
+// old values: the hash algorithm (baked into the live map), the persistence
+// path (where the snapshot loaded at startup goes back to) and the number of
+// value workers (the pool is already running) are returned so the caller can
+// report them; the key normalizer is kept silently, since stored keys went
+// through it, and a manual clock, installed on the loop thread at startup, is
+// ignored.
+fn reload_config(config: &mut CacheConfig, new_config: CacheConfig) -> Vec<&'static str> {
+    let mut restart_required = Vec::new();
+    if new_config.hash_algorithm != config.hash_algorithm {
+        restart_required.push("hash_algorithm");
+    }
+    #[cfg(feature = "persistence")]
+    if new_config.persistence_path != config.persistence_path {
+        restart_required.push("persistence_path");
+    }
+    if new_config.value_workers != config.value_workers {
+        restart_required.push("value_workers");
+    }
+
+    // Keys already stored were normalized by the running normalizer, so
+    // switching it would strand them
+    let hash_algorithm = config.hash_algorithm;
+    let value_workers = config.value_workers;
+    let key_normalizer = config.key_normalizer.take();
+    #[cfg(feature = "persistence")]
+    let persistence_path = config.persistence_path.take();
+    *config = new_config;
+    config.hash_algorithm = hash_algorithm;
+    config.value_workers = value_workers;
+    config.key_normalizer = key_normalizer;
+    #[cfg(feature = "persistence")]
+    {
+        config.persistence_path = persistence_path;
     }
+    restart_required
+}
 
-    for key in expired_keys {
-        if data.remove(&key).is_some() {
-            stats.evictions += 1;
+// Evicts everything above max_size in a single pass over the map, once
+// entries past their deadline are gone
+fn enforce_max_size(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    config: &CacheConfig,
+    stats: &mut CacheStats,
+) -> Result<(), CacheError> {
+    if let Some(max_size) = config.max_size {
+        if data.len() > max_size.get() {
+            expire_due(data, expiration_queue, stats);
         }
+        let excess = data.len().saturating_sub(max_size.get());
+        evict_entries(data, excess.min(data.len()), stats)?;
+    }
+    Ok(())
+}
+
+// Give memory back after a traffic spike instead of staying pinned at the
+// high-water mark; never shrink below the configured initial capacity.
+fn shrink_if_sparse(
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+    config: &CacheConfig,
+) {
+    shrink_entry_map(data, config);
+    if expiration_queue.len() * 4 < expiration_queue.capacity() {
+        expiration_queue.shrink_to(expiration_queue.len() * 2);
+    }
+}
+
+#[cfg(not(feature = "persistent-map"))]
+fn shrink_entry_map(data: &mut EntryMap, config: &CacheConfig) {
+    if data.capacity() > config.initial_capacity && data.len() * 4 < data.capacity() {
+        data.shrink_to((data.len() * 2).max(config.initial_capacity));
     }
 }
 
-fn evict_entry(
-    data: &mut HashMap<String, CacheEntry>,
+// A persistent map frees nodes as entries are removed and has no spare capacity to give back
+#[cfg(feature = "persistent-map")]
+fn shrink_entry_map(_data: &mut EntryMap, _config: &CacheConfig) {}
+
+fn evict_entries(
+    data: &mut EntryMap,
+    count: usize,
     stats: &mut CacheStats,
 ) -> Result<(), CacheError> {
-    if let Some((key_to_remove, _)) = data.iter()
-        .min_by_key(|(_, entry)| (entry.last_accessed, entry.access_count)) {
-        let key_to_remove = key_to_remove.clone();
-        data.remove(&key_to_remove);
-        stats.evictions += 1;
+    if count == 0 {
+        return Ok(());
+    }
+    if count == 1 {
+        if let Some((key_to_remove, _)) = data.iter()
+            .min_by_key(|(_, entry)| (entry.last_accessed, entry.access_count)) {
+            let key_to_remove = key_to_remove.clone();
+            data.remove(&key_to_remove);
+            stats.record_eviction(&key_to_remove);
+        }
+        return Ok(());
+    }
+
+    let mut candidates: Vec<(&String, (Instant, u64))> = data.iter()
+        .map(|(key, entry)| (key, (entry.last_accessed, entry.access_count)))
+        .collect();
+    if count < candidates.len() {
+        candidates.select_nth_unstable_by_key(count - 1, |(_, rank)| *rank);
+        candidates.truncate(count);
+    }
+
+    let keys_to_remove: Vec<String> = candidates.into_iter().map(|(key, _)| key.clone()).collect();
+    for key in keys_to_remove {
+        data.remove(&key);
+        stats.record_eviction(&key);
     }
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+#[cfg(feature = "testing")]
+use std::cell::RefCell;
+#[cfg(feature = "testing")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "testing")]
+use std::sync::Arc;
+#[cfg(feature = "testing")]
+use std::time::Duration;
+use std::time::Instant;
+
+// The event loop reads the time through here when deciding what has expired,
+// so tests can install a ManualClock and move time forward without sleeping.
+// Latency and idle-time measurements keep reading Instant directly.
+#[cfg(not(feature = "testing"))]
+#[inline]
+pub(crate) fn now() -> Instant {
+    Instant::now()
+}
+
+#[cfg(feature = "testing")]
+thread_local! {
+    static MANUAL: RefCell<Option<ManualClock>> = const { RefCell::new(None) };
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn now() -> Instant {
+    MANUAL.with(|clock| clock.borrow().as_ref().map_or_else(Instant::now, ManualClock::now))
+}
+
+// Called once on the event loop thread before it handles any command
+#[cfg(feature = "testing")]
+pub(crate) fn install(clock: Option<ManualClock>) {
+    MANUAL.with(|installed| *installed.borrow_mut() = clock);
+}
+
+// Starts at the real time it was created and then only moves when advanced.
+// Clones share one time, so a test keeps a clone to drive the cache's clock.
+#[cfg(feature = "testing")]
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed_nanos: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "testing")]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "testing")]
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock { start: Instant::now(), elapsed_nanos: Arc::new(AtomicU64::new(0)) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.elapsed_nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    pub fn now(&self) -> Instant {
+        self.start + Duration::from_nanos(self.elapsed_nanos.load(Ordering::SeqCst))
+    }
+}
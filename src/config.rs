@@ -1,12 +1,49 @@
 use std::num::NonZeroUsize;
+#[cfg(feature = "persistence")]
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::alarms::AlarmThresholds;
+#[cfg(feature = "testing")]
+use crate::clock::ManualClock;
+use crate::error::CacheError;
+use crate::middleware::CacheMiddleware;
+
+// Fast suits in-process use; DosResistant (SipHash with random keys) should be
+// used when keys come from untrusted clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Fast,
+    DosResistant,
+}
+
+pub(crate) type KeyNormalizer = dyn Fn(&str) -> String + Send + Sync;
 
 #[derive(Clone)]
 pub struct CacheConfig {
     pub(crate) max_size: Option<NonZeroUsize>,
     pub(crate) default_ttl: Option<Duration>,
     pub(crate) cleanup_interval: Duration,
+    #[cfg(feature = "persistence")]
+    pub(crate) persistence_path: Option<PathBuf>,
+    pub(crate) keep_ttl: bool,
+    pub(crate) initial_capacity: usize,
+    pub(crate) deduplicate_values: bool,
+    pub(crate) checksums: bool,
+    pub(crate) negative_ttl: Duration,
+    pub(crate) hash_algorithm: HashAlgorithm,
+    pub(crate) bulk_chunk_size: NonZeroUsize,
+    pub(crate) slow_command_threshold: Duration,
+    pub(crate) middleware: Vec<Arc<dyn CacheMiddleware>>,
+    pub(crate) key_normalizer: Option<Arc<KeyNormalizer>>,
+    pub(crate) alarms: AlarmThresholds,
+    pub(crate) tracked_prefixes: Vec<String>,
+    pub(crate) simulate_policies: bool,
+    pub(crate) sizing_curve: bool,
+    pub(crate) value_workers: usize,
+    #[cfg(feature = "testing")]
+    pub(crate) clock: Option<ManualClock>,
 }
 
 impl Default for CacheConfig {
@@ -15,6 +52,25 @@ impl Default for CacheConfig {
             max_size: None,
             default_ttl: None,
             cleanup_interval: Duration::from_secs(1),
+            #[cfg(feature = "persistence")]
+            persistence_path: None,
+            keep_ttl: false,
+            initial_capacity: 0,
+            deduplicate_values: false,
+            checksums: false,
+            negative_ttl: Duration::from_secs(30),
+            hash_algorithm: HashAlgorithm::Fast,
+            bulk_chunk_size: NonZeroUsize::new(1024).unwrap(),
+            slow_command_threshold: Duration::from_millis(10),
+            middleware: Vec::new(),
+            key_normalizer: None,
+            alarms: AlarmThresholds::default(),
+            tracked_prefixes: Vec::new(),
+            simulate_policies: false,
+            sizing_curve: false,
+            value_workers: 0,
+            #[cfg(feature = "testing")]
+            clock: None,
         }
     }
 }
@@ -38,4 +94,208 @@ impl CacheConfig {
         self.cleanup_interval = interval;
         self
     }
-}
\ No newline at end of file
+
+    pub fn with_initial_capacity(mut self, capacity: usize) -> Self {
+        self.initial_capacity = capacity;
+        self
+    }
+
+    pub fn with_hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    pub fn with_value_deduplication(mut self, enabled: bool) -> Self {
+        self.deduplicate_values = enabled;
+        self
+    }
+
+    // Stores a checksum with every value and verifies it on reads, evicting
+    // entries that no longer match
+    pub fn with_checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
+    }
+
+    // How long set_negative remembers a missing key when no TTL is given
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    pub fn with_keep_ttl(mut self, keep_ttl: bool) -> Self {
+        self.keep_ttl = keep_ttl;
+        self
+    }
+
+    // Non-atomic bulk commands larger than this run in chunks, with other
+    // clients' commands served in between
+    pub fn with_bulk_chunk_size(mut self, size: NonZeroUsize) -> Self {
+        self.bulk_chunk_size = size;
+        self
+    }
+
+    // Commands whose handler runs at least this long are logged at warn level
+    // when the `tracing` feature is enabled
+    pub fn with_slow_command_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_command_threshold = threshold;
+        self
+    }
+
+    // Rewrites every key before any command sees it, e.g. lowercasing or
+    // trimming, so near-duplicate spellings share one entry. Should be
+    // idempotent. Fixed for the cache's lifetime: reload_config keeps the
+    // one the cache started with.
+    pub fn with_key_normalizer(mut self, normalize: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.key_normalizer = Some(Arc::new(normalize));
+        self
+    }
+
+    // Hits, misses and evictions are also counted per prefix, reported by
+    // CacheStats::prefix_stats. A key counts toward its longest tracked prefix.
+    pub fn with_tracked_prefixes<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tracked_prefixes = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    // Replays traffic against keys-only LRU, LFU and FIFO caches of max_size
+    // entries and reports their hit rates in CacheStats::policy_simulations.
+    // Costs a few map operations per key on every read and write, and the
+    // simulated caches are sized once at startup. Requires max_size.
+    pub fn with_policy_simulation(mut self, enabled: bool) -> Self {
+        self.simulate_policies = enabled;
+        self
+    }
+
+    // Shadows traffic with keys-only LRU caches at max_size and 10%, 25% and
+    // 50% above it, so CacheStats::sizing_curve shows what growing the cache
+    // would buy. Holds up to 1.5x max_size extra keys per shadow; requires
+    // max_size.
+    pub fn with_sizing_curve(mut self, enabled: bool) -> Self {
+        self.sizing_curve = enabled;
+        self
+    }
+
+    // Reads of values of 64 KiB or more are finished on this many worker
+    // threads: checksum verification and copying the value out no longer
+    // hold up the event loop. Completion callbacks for those reads run on a
+    // worker. 0, the default, does everything on the event loop.
+    pub fn with_value_workers(mut self, threads: usize) -> Self {
+        self.value_workers = threads;
+        self
+    }
+
+    // TTLs, expiry and other timers follow this clock instead of real time.
+    // Fixed when the cache starts; reload_config cannot swap it.
+    #[cfg(feature = "testing")]
+    pub fn with_clock(mut self, clock: ManualClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    // Checked about once a second; see Cache::subscribe_alarms
+    pub fn with_alarms(mut self, thresholds: AlarmThresholds) -> Self {
+        self.alarms = thresholds;
+        self
+    }
+
+    // Hooks run in registration order around every command
+    pub fn with_middleware(mut self, middleware: impl CacheMiddleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    #[cfg(feature = "persistence")]
+    pub fn with_persistence_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.persistence_path = Some(path.into());
+        self
+    }
+    // Rejects settings that would make the cache misbehave at runtime rather
+    // than fail loudly
+    pub fn validate(&self) -> Result<(), CacheError> {
+        if self.cleanup_interval.is_zero() {
+            return Err(CacheError::ConfigError(
+                "cleanup_interval must be greater than zero, or the event loop spins on cleanup".to_string(),
+            ));
+        }
+        if self.negative_ttl.is_zero() {
+            return Err(CacheError::ConfigError(
+                "negative_ttl of zero would forget missing keys as soon as they are recorded".to_string(),
+            ));
+        }
+        if self.default_ttl.is_some_and(|ttl| ttl.is_zero()) {
+            return Err(CacheError::ConfigError(
+                "default_ttl of zero would expire every entry as soon as it is written".to_string(),
+            ));
+        }
+        if let Some(max_size) = self.max_size {
+            if self.initial_capacity > max_size.get() {
+                return Err(CacheError::ConfigError(format!(
+                    "initial_capacity ({}) exceeds max_size ({}); the extra capacity could never be used",
+                    self.initial_capacity, max_size,
+                )));
+            }
+        }
+        if (self.simulate_policies || self.sizing_curve) && self.max_size.is_none() {
+            return Err(CacheError::ConfigError(
+                "policy simulation and sizing curves need max_size; without a limit nothing is ever evicted".to_string(),
+            ));
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(path) = &self.persistence_path {
+            if path.is_dir() {
+                return Err(CacheError::ConfigError(format!(
+                    "persistence_path {} is a directory, expected a file path",
+                    path.display(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Replaces every setting validate() would reject with one that works, for
+    // Cache::new: the default for a zero interval or TTL, max_size for an
+    // oversized initial capacity, and off for the rest. Returns their names.
+    pub(crate) fn repair(&mut self) -> Vec<&'static str> {
+        let defaults = CacheConfig::default();
+        let mut repaired = Vec::new();
+        if self.cleanup_interval.is_zero() {
+            self.cleanup_interval = defaults.cleanup_interval;
+            repaired.push("cleanup_interval");
+        }
+        if self.negative_ttl.is_zero() {
+            self.negative_ttl = defaults.negative_ttl;
+            repaired.push("negative_ttl");
+        }
+        if self.default_ttl.is_some_and(|ttl| ttl.is_zero()) {
+            self.default_ttl = None;
+            repaired.push("default_ttl");
+        }
+        if let Some(max_size) = self.max_size {
+            if self.initial_capacity > max_size.get() {
+                self.initial_capacity = max_size.get();
+                repaired.push("initial_capacity");
+            }
+        }
+        if self.max_size.is_none() {
+            if self.simulate_policies {
+                self.simulate_policies = false;
+                repaired.push("simulate_policies");
+            }
+            if self.sizing_curve {
+                self.sizing_curve = false;
+                repaired.push("sizing_curve");
+            }
+        }
+        #[cfg(feature = "persistence")]
+        if self.persistence_path.as_ref().is_some_and(|path| path.is_dir()) {
+            self.persistence_path = None;
+            repaired.push("persistence_path");
+        }
+        repaired
+    }
+}
@@ -0,0 +1,236 @@
+use std::io::{BufRead, ErrorKind, Read};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::CacheError;
+
+const DELTA_HEADER: &str = "MINIREDIS-DELTA 1";
+
+// Writes since a watermark, for keeping two caches that both accept writes
+// eventually consistent. Each key is a last-writer-wins register: the newer
+// write wins, and equal timestamps are settled by comparing values so both
+// sides pick the same winner.
+#[derive(Debug, Clone)]
+pub struct Delta {
+    pub(crate) entries: Vec<DeltaEntry>,
+    pub(crate) watermark: u64,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DeltaEntry {
+    pub key: String,
+    pub value: String,
+    pub ttl: Option<Duration>,
+    pub written_at: u64,
+}
+
+impl Delta {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Pass this to the next delta_since call to get only newer writes
+    pub fn watermark(&self) -> u64 {
+        self.watermark
+    }
+
+    // Same record layout as snapshots, plus each entry's write timestamp
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = format!("{}\n{}\n", DELTA_HEADER, self.watermark).into_bytes();
+        for entry in &self.entries {
+            let ttl = entry.ttl.map_or_else(|| "-".to_string(), |ttl| ttl.as_millis().to_string());
+            out.extend_from_slice(format!("{} {} {} {}\n", entry.key.len(), entry.value.len(), ttl, entry.written_at).as_bytes());
+            out.extend_from_slice(entry.key.as_bytes());
+            out.extend_from_slice(entry.value.as_bytes());
+            out.push(b'\n');
+        }
+        out
+    }
+
+    pub fn decode(mut bytes: &[u8]) -> Result<Delta, CacheError> {
+        let mut line = String::new();
+        bytes.read_line(&mut line).map_err(malformed)?;
+        if line.trim_end() != DELTA_HEADER {
+            return Err(CacheError::SerializationError("unrecognized delta header".to_string()));
+        }
+        line.clear();
+        read_whole_line(&mut bytes, &mut line)?;
+        let watermark = parse_field(Some(line.trim_end()))?;
+
+        let mut entries = Vec::new();
+        loop {
+            line.clear();
+            if bytes.is_empty() {
+                return Ok(Delta { entries, watermark });
+            }
+            read_whole_line(&mut bytes, &mut line)?;
+
+            let mut fields = line.split_whitespace();
+            let key_len = parse_field(fields.next())? as usize;
+            let value_len = parse_field(fields.next())? as usize;
+            let ttl = match fields.next() {
+                Some("-") => None,
+                ttl => Some(Duration::from_millis(parse_field(ttl)?)),
+            };
+            // Applying the delta adds the TTL to the current instant
+            if ttl.is_some_and(|ttl| Instant::now().checked_add(ttl).is_none()) {
+                return Err(malformed_record());
+            }
+            let written_at = parse_field(fields.next())?;
+
+            let key = read_string(&mut bytes, key_len)?;
+            let value = read_string(&mut bytes, value_len)?;
+            let mut newline = [0u8; 1];
+            bytes.read_exact(&mut newline).map_err(malformed)?;
+
+            entries.push(DeltaEntry { key, value, ttl, written_at });
+        }
+    }
+}
+
+// Microseconds since the Unix epoch; comparable across processes and hosts
+// as far as their clocks agree
+pub(crate) fn wall_clock_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+// A line cut off before its newline is a truncated delta, not a shorter
+// number
+fn read_whole_line(reader: &mut impl BufRead, line: &mut String) -> Result<(), CacheError> {
+    reader.read_line(line).map_err(malformed)?;
+    if !line.ends_with('\n') {
+        return Err(malformed(ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+fn parse_field(field: Option<&str>) -> Result<u64, CacheError> {
+    field
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(malformed_record)
+}
+
+fn malformed_record() -> CacheError {
+    CacheError::SerializationError("malformed delta record".to_string())
+}
+
+// Deltas arrive from another process; a bogus length must not turn into a
+// huge up-front allocation
+fn read_string(reader: &mut impl Read, len: usize) -> Result<String, CacheError> {
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf).map_err(malformed)?;
+    if buf.len() < len {
+        return Err(malformed(ErrorKind::UnexpectedEof.into()));
+    }
+    String::from_utf8(buf).map_err(|e| CacheError::SerializationError(e.to_string()))
+}
+
+fn malformed(e: std::io::Error) -> CacheError {
+    CacheError::SerializationError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::config::CacheConfig;
+
+    fn entry(key: &str, value: &str, ttl: Option<Duration>, written_at: u64) -> DeltaEntry {
+        DeltaEntry { key: key.to_string(), value: value.to_string(), ttl, written_at }
+    }
+
+    fn fields(delta: &Delta) -> Vec<(&str, &str, Option<Duration>, u64)> {
+        delta.entries.iter()
+            .map(|entry| (entry.key.as_str(), entry.value.as_str(), entry.ttl, entry.written_at))
+            .collect()
+    }
+
+    fn sample() -> Delta {
+        Delta {
+            entries: vec![
+                entry("user:1", "alice", None, 1_700_000_000_000_000),
+                entry("spaced key", "multi\nline value", Some(Duration::from_millis(1500)), 42),
+                entry("ключ", "", Some(Duration::ZERO), 0),
+                entry("", "empty key", None, u64::MAX),
+            ],
+            watermark: 1_700_000_000_000_001,
+        }
+    }
+
+    #[test]
+    fn decode_inverts_encode() {
+        let delta = sample();
+        let decoded = Delta::decode(&delta.encode()).unwrap();
+        assert_eq!(decoded.watermark(), delta.watermark());
+        assert_eq!(fields(&decoded), fields(&delta));
+
+        let empty = Delta { entries: Vec::new(), watermark: 7 };
+        let decoded = Delta::decode(&empty.encode()).unwrap();
+        assert!(decoded.is_empty());
+        assert_eq!(decoded.watermark(), 7);
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let bytes = sample().encode();
+        let record_ends: Vec<usize> = {
+            let mut ends = Vec::new();
+            let mut decoded = Delta { entries: Vec::new(), watermark: sample().watermark };
+            ends.push(decoded.encode().len());
+            for entry in sample().entries {
+                decoded.entries.push(entry);
+                ends.push(decoded.encode().len());
+            }
+            ends
+        };
+
+        for cut in 0..bytes.len() {
+            match Delta::decode(&bytes[..cut]) {
+                // Only a cut between whole records decodes, to the records before it
+                Ok(decoded) => {
+                    let whole = record_ends.iter().position(|&end| end == cut).expect("decoded a partial record");
+                    assert_eq!(fields(&decoded), fields(&sample())[..whole]);
+                },
+                Err(error) => assert!(matches!(error, CacheError::SerializationError(_))),
+            }
+        }
+        assert!(Delta::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Delta::decode(b"").is_err());
+        assert!(Delta::decode(b"MINIREDIS-DELTA 1\n").is_err());
+    }
+
+    #[test]
+    fn higher_timestamp_wins() {
+        let cache = Cache::new(CacheConfig::new());
+        let merge = |value: &str, written_at| {
+            cache.merge_delta(Delta { entries: vec![entry("k", value, None, written_at)], watermark: written_at }).unwrap()
+        };
+
+        assert_eq!(merge("first", 100), 1);
+        assert_eq!(merge("older", 50), 0);
+        assert_eq!(cache.get("k").unwrap().as_deref(), Some("first"));
+        assert_eq!(merge("newer", 200), 1);
+        assert_eq!(cache.get("k").unwrap().as_deref(), Some("newer"));
+    }
+
+    #[test]
+    fn equal_timestamps_pick_the_same_winner_on_both_sides() {
+        let left = Cache::new(CacheConfig::new());
+        let right = Cache::new(CacheConfig::new());
+        let write = |value: &str| Delta { entries: vec![entry("k", value, None, 100)], watermark: 100 };
+
+        // Each side sees the two concurrent writes in the opposite order
+        left.merge_delta(write("apple")).unwrap();
+        left.merge_delta(write("banana")).unwrap();
+        right.merge_delta(write("banana")).unwrap();
+        right.merge_delta(write("apple")).unwrap();
+
+        assert_eq!(left.get("k").unwrap().as_deref(), Some("banana"));
+        assert_eq!(right.get("k").unwrap().as_deref(), Some("banana"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{HashMap, HashSet};
+
+// Which entries were derived from which keys, kept in both directions: the
+// reverse index answers "what must go when this key changes", the forward one
+// lets an entry's edges be dropped when the entry itself goes away.
+#[derive(Debug, Default)]
+pub(crate) struct DependencyGraph {
+    dependents: HashMap<String, HashSet<String>>,
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    // Replaces whatever `key` depended on before
+    pub fn set_dependencies(&mut self, key: &str, dependencies: Vec<String>) {
+        self.forget(key);
+        if dependencies.is_empty() {
+            return;
+        }
+        for dependency in &dependencies {
+            self.dependents.entry(dependency.clone()).or_default().insert(key.to_string());
+        }
+        self.dependencies.insert(key.to_string(), dependencies);
+    }
+
+    // Drops the edges from `key` to the keys it depends on
+    pub fn forget(&mut self, key: &str) {
+        if let Some(dependencies) = self.dependencies.remove(key) {
+            unlink(&mut self.dependents, key, dependencies);
+        }
+    }
+
+    // Everything that transitively depends on `key`, removed from the graph.
+    // `key` itself is never included, even when the dependencies form a cycle.
+    pub fn take_dependents(&mut self, key: &str) -> Vec<String> {
+        let Some(direct) = self.dependents.remove(key) else {
+            return Vec::new();
+        };
+
+        let mut seen = HashSet::from([key.to_string()]);
+        let mut pending: Vec<String> = direct.into_iter().collect();
+        let mut invalidated = Vec::new();
+        while let Some(dependent) = pending.pop() {
+            if !seen.insert(dependent.clone()) {
+                continue;
+            }
+            if let Some(next) = self.dependents.remove(&dependent) {
+                pending.extend(next);
+            }
+            self.forget(&dependent);
+            invalidated.push(dependent);
+        }
+        invalidated
+    }
+
+    // Drops edges of entries that went away without a forget, e.g. evicted
+    pub fn retain(&mut self, mut is_live: impl FnMut(&str) -> bool) {
+        let mut removed = Vec::new();
+        self.dependencies.retain(|key, dependencies| {
+            let live = is_live(key);
+            if !live {
+                removed.push((key.clone(), std::mem::take(dependencies)));
+            }
+            live
+        });
+        for (key, dependencies) in removed {
+            unlink(&mut self.dependents, &key, dependencies);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.dependents.clear();
+        self.dependencies.clear();
+    }
+}
+
+// Removes `key` from the reverse index of each key it depended on
+fn unlink(dependents: &mut HashMap<String, HashSet<String>>, key: &str, dependencies: Vec<String>) {
+    for dependency in dependencies {
+        if let Some(keys) = dependents.get_mut(&dependency) {
+            keys.remove(key);
+            if keys.is_empty() {
+                dependents.remove(&dependency);
+            }
+        }
+    }
+}
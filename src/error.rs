@@ -3,20 +3,32 @@ use std::fmt;
 #[derive(Debug, Clone)]
 pub enum CacheError {
     KeyNotFound,
-    ValueNotInteger,
+    ValueNotNumeric(String),
+    CounterOutOfRange,
     KeyExpired,
     SerializationError(String),
     LockError,
+    PersistenceError(String),
+    ConfigError(String),
+    CorruptedEntry(String),
+    Timeout,
+    Overloaded,
 }
 
 impl fmt::Display for CacheError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             CacheError::KeyNotFound => write!(f, "Key not found in the cache"),
-            CacheError::ValueNotInteger => write!(f, "Value is not an integer"),
+            CacheError::ValueNotNumeric(value) => write!(f, "Value is not a valid number: {:?}", value),
+            CacheError::CounterOutOfRange => write!(f, "Increment would move the counter outside its bounds"),
             CacheError::KeyExpired => write!(f, "Key has expired"),
             CacheError::SerializationError(msg) => write!(f, "Serialization error: {}", msg),
             CacheError::LockError => write!(f, "Failed to acquire the lock"),
+            CacheError::PersistenceError(msg) => write!(f, "Persistence error: {}", msg),
+            CacheError::ConfigError(msg) => write!(f, "Invalid configuration: {}", msg),
+            CacheError::CorruptedEntry(key) => write!(f, "Stored value for {:?} failed its checksum and was evicted", key),
+            CacheError::Timeout => write!(f, "Command timed out"),
+            CacheError::Overloaded => write!(f, "Cache is overloaded; command was shed"),
         }
     }
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::cache::Cache;
+use crate::error::CacheError;
+
+// What a matching call gets instead of the real result
+#[derive(Debug, Clone, PartialEq)]
+pub enum Failure {
+    Timeout,
+    Overloaded,
+    // Only applies to get: answers with this value instead of the stored one
+    Stale(String),
+}
+
+// Which calls fail and how often. Without on_key or on_command a rule matches
+// every call; without times it keeps firing.
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    failure: Failure,
+    key: Option<String>,
+    command: Option<&'static str>,
+    skip: usize,
+    remaining: Option<usize>,
+}
+
+impl FaultRule {
+    pub fn new(failure: Failure) -> Self {
+        FaultRule { failure, key: None, command: None, skip: 0, remaining: None }
+    }
+
+    pub fn on_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    // Uses the command names from tracing and middleware: "get", "set",
+    // "delete", "update_ttl", "compare_and_swap"
+    pub fn on_command(mut self, command: &'static str) -> Self {
+        self.command = Some(command);
+        self
+    }
+
+    // The first `calls` matching calls pass this rule by; later rules still apply
+    pub fn after(mut self, calls: usize) -> Self {
+        self.skip = calls;
+        self
+    }
+
+    // times(0) makes a rule that never fires; inject drops it
+    pub fn times(mut self, times: usize) -> Self {
+        self.remaining = Some(times);
+        self
+    }
+
+    fn matches(&self, command: &str, key: &str) -> bool {
+        self.command.is_none_or(|expected| expected == command)
+            && self.key.as_deref().is_none_or(|expected| expected == key)
+            && (command == "get" || !matches!(self.failure, Failure::Stale(_)))
+    }
+}
+
+// Test double for exercising an application's cache-failure handling. Wraps
+// a real Cache and fails the calls that match a scripted FaultRule, checked
+// in the order they were injected. Failed calls never reach the cache.
+// Anything not wrapped here is available through `cache()`, unfaulted.
+pub struct FaultInjectingCache {
+    cache: Cache,
+    rules: Mutex<Vec<FaultRule>>,
+    injected: Mutex<HashMap<&'static str, u64>>,
+}
+
+impl FaultInjectingCache {
+    pub fn new(cache: Cache) -> Self {
+        FaultInjectingCache { cache, rules: Mutex::new(Vec::new()), injected: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn inject(&self, rule: FaultRule) {
+        if rule.remaining == Some(0) {
+            return;
+        }
+        self.rules.lock().unwrap().push(rule);
+    }
+
+    pub fn clear_faults(&self) {
+        self.rules.lock().unwrap().clear();
+    }
+
+    // How many calls of `command` were failed so far
+    pub fn injected(&self, command: &str) -> u64 {
+        self.injected.lock().unwrap().get(command).copied().unwrap_or(0)
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, CacheError> {
+        match self.fault("get", key) {
+            Some(Failure::Stale(value)) => Ok(Some(value)),
+            Some(failure) => Err(error_for(failure)),
+            None => self.cache.get(key),
+        }
+    }
+
+    pub fn set(&self, key: String, value: String, ttl: Option<Duration>) -> Result<(), CacheError> {
+        self.check("set", &key)?;
+        self.cache.set(key, value, ttl)
+    }
+
+    pub fn delete(&self, key: &str) -> Result<bool, CacheError> {
+        self.check("delete", key)?;
+        self.cache.delete(key)
+    }
+
+    pub fn update_ttl(&self, key: &str, ttl: Duration) -> Result<bool, CacheError> {
+        self.check("update_ttl", key)?;
+        self.cache.update_ttl(key, ttl)
+    }
+
+    pub fn compare_and_swap(&self, key: &str, expected: &str, new_value: String) -> Result<bool, CacheError> {
+        self.check("compare_and_swap", key)?;
+        self.cache.compare_and_swap(key, expected, new_value)
+    }
+
+    fn check(&self, command: &'static str, key: &str) -> Result<(), CacheError> {
+        self.fault(command, key).map_or(Ok(()), |failure| Err(error_for(failure)))
+    }
+
+    fn fault(&self, command: &'static str, key: &str) -> Option<Failure> {
+        let mut rules = self.rules.lock().unwrap();
+        let index = rules.iter_mut().position(|rule| {
+            if !rule.matches(command, key) {
+                return false;
+            }
+            if rule.skip > 0 {
+                rule.skip -= 1;
+                return false;
+            }
+            true
+        })?;
+
+        let failure = rules[index].failure.clone();
+        if let Some(remaining) = &mut rules[index].remaining {
+            *remaining -= 1;
+            if *remaining == 0 {
+                rules.remove(index);
+            }
+        }
+        *self.injected.lock().unwrap().entry(command).or_default() += 1;
+        Some(failure)
+    }
+}
+
+fn error_for(failure: Failure) -> CacheError {
+    match failure {
+        Failure::Timeout => CacheError::Timeout,
+        Failure::Overloaded => CacheError::Overloaded,
+        Failure::Stale(_) => unreachable!("stale rules only match get"),
+    }
+}
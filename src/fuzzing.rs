@@ -0,0 +1,31 @@
+use std::collections::BinaryHeap;
+
+use crate::config::HashAlgorithm;
+use crate::delta::Delta;
+use crate::error::CacheError;
+use crate::hashing::KeyHashBuilder;
+use crate::persistence::read_snapshot;
+use crate::stream::StreamChunk;
+use crate::types::EntryMap;
+
+// Entry points for the cargo-fuzz targets in fuzz/, taking raw bytes so the
+// decoders can be reached without files or a running cache. Malformed input
+// must come back as an error; any panic or abort is a bug.
+
+// Returns how many entries were loaded
+pub fn load_snapshot(bytes: &[u8]) -> Result<usize, CacheError> {
+    let mut data = EntryMap::with_hasher(KeyHashBuilder::new(HashAlgorithm::Fast));
+    let mut expiration_queue = BinaryHeap::new();
+    read_snapshot(bytes, &mut data, &mut expiration_queue)?;
+    Ok(data.len())
+}
+
+// Returns how many entries were decoded
+pub fn decode_delta(bytes: &[u8]) -> Result<usize, CacheError> {
+    Delta::decode(bytes).map(|delta| delta.len())
+}
+
+// Returns how many payload bytes the chunk carries
+pub fn decode_chunk(bytes: &[u8]) -> Result<usize, CacheError> {
+    StreamChunk::decode(bytes).map(|chunk| chunk.len())
+}
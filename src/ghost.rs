@@ -0,0 +1,124 @@
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulatedPolicy {
+    Lru,
+    Lfu,
+    Fifo,
+}
+
+// What the hit rate would have been under `policy` at `capacity` entries
+#[derive(Debug, Clone)]
+pub struct PolicySimulation {
+    pub(crate) policy: SimulatedPolicy,
+    pub(crate) capacity: usize,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+}
+
+impl PolicySimulation {
+    pub fn policy(&self) -> SimulatedPolicy {
+        self.policy
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            (self.hits as f64 / total as f64) * 100.0
+        }
+    }
+}
+
+// A keys-only cache that replays the real traffic under another eviction
+// policy. Entries are ranked so the smallest rank goes first: LRU ranks by
+// last use, FIFO by first insert, LFU by use count with last use as the
+// tie-break.
+#[derive(Debug)]
+pub(crate) struct GhostCache {
+    simulation: PolicySimulation,
+    ranks: HashMap<String, (u64, u64)>,
+    order: BTreeSet<((u64, u64), String)>,
+    tick: u64,
+}
+
+impl GhostCache {
+    pub fn new(policy: SimulatedPolicy, capacity: usize) -> Self {
+        GhostCache {
+            simulation: PolicySimulation { policy, capacity, hits: 0, misses: 0 },
+            ranks: HashMap::new(),
+            order: BTreeSet::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn simulation(&self) -> &PolicySimulation {
+        &self.simulation
+    }
+
+    pub fn read(&mut self, key: &str) {
+        if self.touch(key) {
+            self.simulation.hits += 1;
+        } else {
+            self.simulation.misses += 1;
+        }
+    }
+
+    pub fn write(&mut self, key: &str) {
+        if self.touch(key) {
+            return;
+        }
+        if self.ranks.len() >= self.simulation.capacity {
+            if let Some((_, evicted)) = self.order.pop_first() {
+                self.ranks.remove(&evicted);
+            }
+        }
+        let rank = match self.simulation.policy {
+            SimulatedPolicy::Lfu => (1, self.tick),
+            SimulatedPolicy::Lru | SimulatedPolicy::Fifo => (self.tick, 0),
+        };
+        self.ranks.insert(key.to_string(), rank);
+        self.order.insert((rank, key.to_string()));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(rank) = self.ranks.remove(key) {
+            self.order.remove(&(rank, key.to_string()));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ranks.clear();
+        self.order.clear();
+    }
+
+    // Counts a use of `key` if it is resident; returns whether it was
+    fn touch(&mut self, key: &str) -> bool {
+        self.tick += 1;
+        let Some(rank) = self.ranks.get_mut(key) else {
+            return false;
+        };
+        let updated = match self.simulation.policy {
+            SimulatedPolicy::Lru => (self.tick, 0),
+            SimulatedPolicy::Lfu => (rank.0 + 1, self.tick),
+            SimulatedPolicy::Fifo => return true,
+        };
+        self.order.remove(&(*rank, key.to_string()));
+        *rank = updated;
+        self.order.insert((updated, key.to_string()));
+        true
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hasher};
+
+use rustc_hash::FxHasher;
+
+use crate::config::HashAlgorithm;
+
+#[derive(Clone)]
+pub(crate) enum KeyHashBuilder {
+    Fast,
+    DosResistant(RandomState),
+}
+
+impl KeyHashBuilder {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Fast => KeyHashBuilder::Fast,
+            HashAlgorithm::DosResistant => KeyHashBuilder::DosResistant(RandomState::new()),
+        }
+    }
+}
+
+impl BuildHasher for KeyHashBuilder {
+    type Hasher = KeyHasher;
+
+    fn build_hasher(&self) -> KeyHasher {
+        match self {
+            KeyHashBuilder::Fast => KeyHasher::Fast(FxHasher::default()),
+            KeyHashBuilder::DosResistant(state) => KeyHasher::DosResistant(state.build_hasher()),
+        }
+    }
+}
+
+pub(crate) enum KeyHasher {
+    Fast(FxHasher),
+    DosResistant(DefaultHasher),
+}
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        match self {
+            KeyHasher::Fast(hasher) => hasher.finish(),
+            KeyHasher::DosResistant(hasher) => hasher.finish(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            KeyHasher::Fast(hasher) => hasher.write(bytes),
+            KeyHasher::DosResistant(hasher) => hasher.write(bytes),
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        match self {
+            KeyHasher::Fast(hasher) => hasher.write_u8(i),
+            KeyHasher::DosResistant(hasher) => hasher.write_u8(i),
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        match self {
+            KeyHasher::Fast(hasher) => hasher.write_usize(i),
+            KeyHasher::DosResistant(hasher) => hasher.write_usize(i),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::cache::Cache;
+use crate::error::CacheError;
+
+// Members ranked by score, highest first, ties broken by member name. The
+// ordered set gives O(log n) updates and walks from the top or from any
+// member; the map finds a member's current score to move it. Ranks are
+// counted by walking from the top, so they cost O(rank).
+#[derive(Debug)]
+pub(crate) struct Board {
+    scores: HashMap<String, f64>,
+    ranking: BTreeSet<(Reverse<Score>, String)>,
+    reset_every: Option<Duration>,
+    next_reset: Option<Instant>,
+}
+
+// f64 ordered with total_cmp, so the set needs no NaN handling; non-finite
+// scores are rejected before they get here
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Board {
+    pub fn new(reset_every: Option<Duration>, now: Instant) -> Self {
+        Board {
+            scores: HashMap::new(),
+            ranking: BTreeSet::new(),
+            reset_every,
+            next_reset: reset_every.map(|every| now + every),
+        }
+    }
+
+    // Empties the board once per period counted from its creation. Periods
+    // that passed with nobody looking are skipped rather than replayed.
+    pub fn reset_if_due(&mut self, now: Instant) {
+        let (Some(every), Some(next_reset)) = (self.reset_every, self.next_reset.as_mut()) else {
+            return;
+        };
+        if now < *next_reset {
+            return;
+        }
+        while *next_reset <= now {
+            *next_reset += every;
+        }
+        self.scores.clear();
+        self.ranking.clear();
+    }
+
+    pub fn add(&mut self, member: &str, delta: f64) -> Result<f64, CacheError> {
+        let current = self.scores.get(member).copied();
+        let updated = current.unwrap_or(0.0) + delta;
+        if !updated.is_finite() {
+            return Err(CacheError::ValueNotNumeric(updated.to_string()));
+        }
+
+        match current {
+            Some(current) => {
+                let mut entry = (Reverse(Score(current)), member.to_string());
+                self.ranking.remove(&entry);
+                entry.0 = Reverse(Score(updated));
+                self.ranking.insert(entry);
+            },
+            None => {
+                self.ranking.insert((Reverse(Score(updated)), member.to_string()));
+            },
+        }
+        self.scores.insert(member.to_string(), updated);
+        Ok(updated)
+    }
+
+    pub fn top(&self, n: usize) -> Vec<(String, f64)> {
+        self.ranking.iter().take(n).map(ranked).collect()
+    }
+
+    // 0 for the highest score
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let score = *self.scores.get(member)?;
+        Some(self.ranking.range(..(Reverse(Score(score)), member.to_string())).count())
+    }
+
+    // The member with up to `window` neighbours on either side, in rank order
+    pub fn around(&self, member: &str, window: usize) -> Vec<(String, f64)> {
+        let Some(score) = self.scores.get(member) else {
+            return Vec::new();
+        };
+        let position = (Reverse(Score(*score)), member.to_string());
+        let mut around: Vec<_> = self.ranking.range(..&position).rev().take(window).map(ranked).collect();
+        around.reverse();
+        around.extend(self.ranking.range(&position..).take(window + 1).map(ranked));
+        around
+    }
+}
+
+fn ranked((score, member): &(Reverse<Score>, String)) -> (String, f64) {
+    (member.clone(), score.0 .0)
+}
+
+// A named leaderboard kept on the cache's event loop. Scores live beside the
+// cached data, like top-k trackers: they have no TTL, are not evicted or
+// persisted, and are cleared by flush.
+pub struct Leaderboard<'a> {
+    cache: &'a Cache,
+    name: String,
+}
+
+impl<'a> Leaderboard<'a> {
+    // Creates the board if there is none under `name` yet; an existing one
+    // keeps its scores and reset period. With `reset_every`, the board empties
+    // itself once per period from when it was created, e.g. a daily board.
+    pub fn open(cache: &'a Cache, name: impl Into<String>, reset_every: Option<Duration>) -> Result<Self, CacheError> {
+        if reset_every.is_some_and(|every| every.is_zero()) {
+            return Err(CacheError::ConfigError("leaderboard reset period must be non-zero".to_string()));
+        }
+        let name = name.into();
+        cache.leaderboard_open(&name, reset_every)?;
+        Ok(Leaderboard { cache, name })
+    }
+
+    // Adds `delta` (possibly negative) to the member's score, starting from 0
+    // for a new member, and returns the new score
+    pub fn add_score(&self, member: &str, delta: f64) -> Result<f64, CacheError> {
+        self.cache.leaderboard_add(&self.name, member, delta)
+    }
+
+    // The `n` highest scores, highest first
+    pub fn top(&self, n: usize) -> Result<Vec<(String, f64)>, CacheError> {
+        self.cache.leaderboard_top(&self.name, n)
+    }
+
+    // 0-based position from the top, or None for a member with no score
+    pub fn rank(&self, member: &str) -> Result<Option<usize>, CacheError> {
+        self.cache.leaderboard_rank(&self.name, member)
+    }
+
+    // The member and up to `window` members ranked directly above and below
+    // it, highest first; empty for a member with no score
+    pub fn around(&self, member: &str, window: usize) -> Result<Vec<(String, f64)>, CacheError> {
+        self.cache.leaderboard_around(&self.name, member, window)
+    }
+}
@@ -1,11 +1,58 @@
+mod alarms;
+mod analysis;
+mod breaker;
 mod cache;
+mod clock;
 mod config;
+mod delta;
+mod dependencies;
 mod error;
+#[cfg(feature = "testing")]
+mod fault;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+mod ghost;
+mod hashing;
+mod leaderboard;
+mod lifecycle;
+mod middleware;
+mod pattern;
+#[cfg(feature = "persistence")]
+mod persistence;
+mod queue;
+mod registry;
+mod session;
+mod snapshot;
 mod stats;
+mod stream;
+mod timeseries;
+mod topk;
+mod transport;
 mod types;
+mod unique;
+mod value;
+mod workers;
 
+pub use alarms::{Alarm, AlarmThresholds};
+pub use analysis::{Distribution, KeyspaceReport, PrefixUsage};
+pub use breaker::{BreakerPolicy, BreakerState};
 pub use cache::Cache;
-pub use config::CacheConfig;
+#[cfg(feature = "testing")]
+pub use clock::ManualClock;
+pub use config::{CacheConfig, HashAlgorithm};
+pub use delta::Delta;
 pub use error::CacheError;
-pub use stats::CacheStats;
+#[cfg(feature = "testing")]
+pub use fault::{Failure, FaultInjectingCache, FaultRule};
+pub use ghost::{PolicySimulation, SimulatedPolicy};
+pub use leaderboard::Leaderboard;
+pub use middleware::{CacheMiddleware, CommandInfo};
+pub use queue::MessageQueue;
+pub use registry::CacheRegistry;
+pub use session::SessionStore;
+pub use snapshot::Snapshot;
+pub use stats::{CacheStats, LatencyHistogram, PrefixStats};
+pub use stream::{SnapshotReceiver, SnapshotStream, StreamChunk};
+pub use timeseries::TimeSeriesCounter;
+pub use types::{CounterBounds, EntryOrder, Expiry, Lookup, ObjectInfo, Overflow, ResponseSlot, ScanOptions, ScanPage, SetOptions};
 // pub use types::ExpirationEntry;
\ No newline at end of file
@@ -0,0 +1,163 @@
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicBool, Ordering};
+#[cfg(loom)]
+use loom::sync::{Arc, Mutex};
+#[cfg(loom)]
+use loom::thread::{self, JoinHandle, ThreadId};
+#[cfg(not(loom))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(loom))]
+use std::sync::{Arc, Mutex};
+#[cfg(not(loom))]
+use std::thread::{self, JoinHandle, ThreadId};
+
+// Cleared once shutdown has been requested; the event loop checks it between
+// commands
+#[derive(Clone)]
+pub(crate) struct RunningFlag(Arc<AtomicBool>);
+
+impl RunningFlag {
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+// The event loop thread, shared by every clone of a Cache. Any clone can ask
+// it to stop, and wait for it with join; otherwise it is joined when the last
+// clone is dropped, so the loop has finished (and persisted) by the time that
+// drop returns. Built on the std or loom primitives depending on `--cfg loom`.
+pub(crate) struct EventLoopHandle {
+    running: Arc<AtomicBool>,
+    wake: Box<dyn Fn() + Send + Sync>,
+    loop_thread: ThreadId,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventLoopHandle {
+    // `wake` must get a blocked loop to look at the flag again, normally by
+    // sending it a shutdown command
+    pub fn spawn(
+        run: impl FnOnce(RunningFlag) + Send + 'static,
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = RunningFlag(running.clone());
+        let thread = thread::spawn(move || run(flag));
+        EventLoopHandle {
+            running,
+            wake: Box::new(wake),
+            loop_thread: thread.thread().id(),
+            thread: Mutex::new(Some(thread)),
+        }
+    }
+
+    // Only the first request wakes the loop
+    pub fn request_shutdown(&self) {
+        if self.running.swap(false, Ordering::AcqRel) {
+            (self.wake)();
+        }
+    }
+
+    // Stops the loop and waits until it has finished. Concurrent callers all
+    // wait, the first one holding the lock while it joins. Returns at once on
+    // the loop thread itself, from a completion callback: the loop exits on
+    // its own once the callback returns.
+    pub fn join(&self) {
+        self.request_shutdown();
+        if self.loop_thread == thread::current().id() {
+            return;
+        }
+        if let Ok(mut thread) = self.thread.lock() {
+            if let Some(thread) = thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+impl Drop for EventLoopHandle {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+// RUSTFLAGS="--cfg loom" cargo test --release --lib lifecycle
+#[cfg(all(test, loom))]
+mod tests {
+    use super::*;
+    use loom::sync::mpsc;
+    use loom::sync::Mutex;
+
+    enum Message {
+        Command,
+        Shutdown,
+    }
+
+    // Where the loop leaves its receiver on exit. The real loop answers any
+    // commands still queued with LockError and drops it; loom reports
+    // undelivered messages as leaks, so tests drain it here instead.
+    type Leftovers = Arc<Mutex<Option<mpsc::Receiver<Message>>>>;
+
+    fn drain(leftovers: &Leftovers) {
+        let receiver = leftovers.lock().unwrap().take().unwrap();
+        while receiver.try_recv().is_ok() {}
+    }
+
+    // The same shape as run_event_loop: handle commands until told to stop
+    fn spawn_loop(leftovers: Leftovers) -> (EventLoopHandle, mpsc::Sender<Message>) {
+        let (sender, receiver) = mpsc::channel();
+        let wake = Mutex::new(sender.clone());
+        let handle = EventLoopHandle::spawn(
+            move |running| {
+                while running.is_set() {
+                    match receiver.recv() {
+                        Ok(Message::Command) => {},
+                        Ok(Message::Shutdown) | Err(_) => break,
+                    }
+                }
+                *leftovers.lock().unwrap() = Some(receiver);
+            },
+            move || {
+                let _ = wake.lock().unwrap().send(Message::Shutdown);
+            },
+        );
+        (handle, sender)
+    }
+
+    #[test]
+    fn last_of_concurrent_drops_joins_the_loop() {
+        loom::model(|| {
+            let leftovers = Leftovers::default();
+            let (handle, _sender) = spawn_loop(leftovers.clone());
+            let first = Arc::new(handle);
+            let second = first.clone();
+
+            let dropper = thread::spawn(move || drop(first));
+            drop(second);
+            dropper.join().unwrap();
+
+            // Set by the loop on its way out, so it has finished
+            drain(&leftovers);
+        });
+    }
+
+    #[test]
+    fn shutdown_with_a_command_in_flight() {
+        loom::model(|| {
+            let leftovers = Leftovers::default();
+            let (handle, sender) = spawn_loop(leftovers.clone());
+            let handle = Arc::new(handle);
+            let stopper = handle.clone();
+
+            let client = thread::spawn(move || {
+                let _ = sender.send(Message::Command);
+            });
+            stopper.request_shutdown();
+            client.join().unwrap();
+            drop(stopper);
+            drop(handle);
+
+            drain(&leftovers);
+        });
+    }
+}
@@ -1,28 +1,81 @@
+use std::env;
 use std::num::NonZeroUsize;
-use std::thread;
+use std::process;
 use std::time::Duration;
 use mini_redis::{Cache, CacheConfig}; 
 
+const USAGE: &str = "usage: mini-redis [--warm-from <snapshot>] [--persist <path>]
+
+--warm-from loads a snapshot file (Snapshot::save, or a cache's persistence path)
+into the cache before anything else runs.
+--persist loads the cache from <path> at startup and writes it back on exit,
+including on SIGINT or SIGTERM.";
+
+#[cfg(feature = "persistence")]
+const WARM_CONCURRENCY: usize = 4;
+
 fn main() {
+    #[cfg(feature = "tracing")]
+    init_logging();
+
+    let args = parse_args().unwrap_or_else(|msg| {
+        eprintln!("{}\n\n{}", msg, USAGE);
+        process::exit(2);
+    });
+    signals::install();
    
     let config = CacheConfig::default()
         .with_max_size(NonZeroUsize::new(1000).unwrap())
         .with_default_ttl(Duration::from_secs(30))
         .with_cleanup_interval(Duration::from_secs(5));
+    let config = with_persistence(config, args.persist).unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        process::exit(2);
+    });
     
     let cache = Cache::new(config);
 
+    if let Some(path) = args.warm_from {
+        if let Err(e) = warm(&cache, &path) {
+            eprintln!("failed to warm from {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+
+    run_demo(&cache);
+
+    // Dropping the last handle stops the event loop after the command it is
+    // running and waits for it, which writes the --persist snapshot
+    drop(cache);
+    if signals::received() {
+        #[cfg(feature = "tracing")]
+        tracing::info!("shut down after termination signal");
+    }
+}
+
+// Cut short if SIGINT or SIGTERM arrives while it waits for a key to expire
+fn run_demo(cache: &Cache) {
+    // The demo reports through tracing events (see init_logging), so it is
+    // silent without the tracing feature
     cache.set("key1".to_string(), "value1".to_string(), None).unwrap();
-    println!("Value: {:?}", cache.get("key1").unwrap());
+    let _value = cache.get("key1").unwrap();
+    #[cfg(feature = "tracing")]
+    tracing::info!(key = "key1", value = ?_value, "get");
 
     cache.set(
         "temp_key".to_string(),
         "temporary".to_string(),
         Some(Duration::from_secs(2))
     ).unwrap();
-    println!("Temp value exists: {:?}", cache.get("temp_key").unwrap());
-    thread::sleep(Duration::from_secs(3));
-    println!("After expiration: {:?}", cache.get("temp_key").unwrap());
+    let _value = cache.get("temp_key").unwrap();
+    #[cfg(feature = "tracing")]
+    tracing::info!(key = "temp_key", value = ?_value, ttl_secs = 2, "get before expiry");
+    if signals::wait(Duration::from_secs(3)) {
+        return;
+    }
+    let _value = cache.get("temp_key").unwrap();
+    #[cfg(feature = "tracing")]
+    tracing::info!(key = "temp_key", value = ?_value, "get after expiry");
 
     let items = vec![
         ("bulk1".to_string(), "value1".to_string()),
@@ -32,24 +85,159 @@ fn main() {
     cache.bulk_set(items).unwrap();
 
     let keys = vec!["bulk1", "bulk2", "bulk3", "nonexistent"];
-    let results = cache.bulk_get(keys).unwrap();
-    println!("Bulk get results: {:?}", results);
+    let _results = cache.bulk_get(keys).unwrap();
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        keys = _results.len(),
+        found = _results.values().filter(|value| value.is_some()).count(),
+        results = ?_results,
+        "bulk get",
+    );
 
     cache.set("cas_key".to_string(), "old_value".to_string(), None).unwrap();
-    let cas_result = cache.compare_and_swap(
+    let _swapped = cache.compare_and_swap(
         "cas_key",
         "old_value",
         "new_value".to_string()
     ).unwrap();
-    println!("CAS operation succeeded: {}", cas_result);
-
-    let stats = cache.get_stats().unwrap();
-    println!("\nCache Statistics:");
-    println!("Hits: {}", stats.hits());
-    println!("Misses: {}", stats.misses());
-    println!("Evictions: {}", stats.evictions());
-    println!("Hit Rate: {:.2}%", stats.hit_rate());
+    #[cfg(feature = "tracing")]
+    tracing::info!(key = "cas_key", swapped = _swapped, "compare and swap");
 
+    let _stats = cache.get_stats().unwrap();
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        hits = _stats.hits(),
+        misses = _stats.misses(),
+        evictions = _stats.evictions(),
+        hit_rate = format_args!("{:.2}", _stats.hit_rate()),
+        "cache statistics",
+    );
 
     cache.update_ttl("key1", Duration::from_secs(60)).unwrap();
-}
\ No newline at end of file
+}
+
+#[derive(Default)]
+struct Args {
+    warm_from: Option<String>,
+    persist: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--warm-from" => parsed.warm_from = Some(args.next().ok_or("--warm-from needs a path")?),
+            "--persist" => parsed.persist = Some(args.next().ok_or("--persist needs a path")?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            other => return Err(format!("unknown argument {:?}", other)),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(feature = "persistence")]
+fn with_persistence(config: CacheConfig, path: Option<String>) -> Result<CacheConfig, String> {
+    Ok(match path {
+        Some(path) => config.with_persistence_path(path),
+        None => config,
+    })
+}
+
+#[cfg(not(feature = "persistence"))]
+fn with_persistence(config: CacheConfig, path: Option<String>) -> Result<CacheConfig, String> {
+    match path {
+        Some(_) => Err("--persist needs the persistence feature".to_string()),
+        None => Ok(config),
+    }
+}
+
+// Entries get the cache's default TTL, not what they had left in the snapshot
+#[cfg(feature = "persistence")]
+fn warm(cache: &Cache, path: &str) -> Result<(), mini_redis::CacheError> {
+    let snapshot = mini_redis::Snapshot::load(path)?;
+    let _total = snapshot.len();
+    let seed = snapshot.iter().map(|(key, value)| (key.to_string(), value.to_string()));
+    let concurrency = NonZeroUsize::new(WARM_CONCURRENCY).unwrap();
+    let _loaded = cache.warm_from(seed, concurrency, |_loaded| {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(loaded = _loaded, total = _total, "warming");
+    })?;
+    #[cfg(feature = "tracing")]
+    tracing::info!(path, entries = _loaded, "warmed cache from snapshot");
+    Ok(())
+}
+
+#[cfg(not(feature = "persistence"))]
+fn warm(_cache: &Cache, _path: &str) -> Result<(), mini_redis::CacheError> {
+    Err(mini_redis::CacheError::ConfigError("--warm-from needs the persistence feature".to_string()))
+}
+
+// RUST_LOG picks what gets logged (info and up by default),
+// MINI_REDIS_LOG_FORMAT=json switches from human-readable lines to one JSON
+// object per event
+#[cfg(feature = "tracing")]
+fn init_logging() {
+    use tracing_subscriber::fmt::format::FmtSpan;
+    use tracing_subscriber::EnvFilter;
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with_span_events(FmtSpan::CLOSE);
+    match std::env::var("MINI_REDIS_LOG_FORMAT").as_deref() {
+        Ok("json") => builder.json().init(),
+        Ok("pretty") => builder.pretty().init(),
+        _ => builder.init(),
+    }
+}
+
+// SIGINT and SIGTERM only raise a flag; the demo checks it while it waits and
+// then shuts the cache down the normal way, so a stop from systemd or
+// Kubernetes still writes the persistence snapshot and exits with status 0
+mod signals {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    static RECEIVED: AtomicBool = AtomicBool::new(false);
+
+    #[cfg(unix)]
+    pub fn install() {
+        extern "C" fn on_signal(_signal: libc::c_int) {
+            // Only an atomic store: anything else isn't async-signal-safe
+            RECEIVED.store(true, Ordering::SeqCst);
+        }
+
+        for signal in [libc::SIGINT, libc::SIGTERM] {
+            // SAFETY: the handler is an extern "C" fn that only stores to an
+            // atomic, which is async-signal-safe
+            unsafe {
+                libc::signal(signal, on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+            }
+        }
+    }
+
+    // Elsewhere the default handlers stay and a signal ends the process
+    #[cfg(not(unix))]
+    pub fn install() {}
+
+    pub fn received() -> bool {
+        RECEIVED.load(Ordering::SeqCst)
+    }
+
+    // Sleeps for `duration`, returning early with true if a signal arrives
+    pub fn wait(duration: Duration) -> bool {
+        let until = Instant::now() + duration;
+        while !received() {
+            let now = Instant::now();
+            if now >= until {
+                return false;
+            }
+            thread::sleep((until - now).min(Duration::from_millis(50)));
+        }
+        true
+    }
+}
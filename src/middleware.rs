@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use crate::error::CacheError;
+
+// What middleware gets to see of a command: its name as used in tracing
+// (`"set"`, `"get"`, `"bulk_set"`, ...) and the key, for single-key commands.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandInfo<'a> {
+    pub(crate) name: &'static str,
+    pub(crate) key: Option<&'a str>,
+}
+
+impl CommandInfo<'_> {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key
+    }
+}
+
+// Hooks run on the event loop thread around every command, in the order they
+// were registered. Like completion callbacks they must be quick and must not
+// call back into the cache. Commands that run in chunks (bulk_set, bulk_get)
+// go through the hooks once per chunk.
+pub trait CacheMiddleware: Send + Sync {
+    // Returning an error fails the command with it before it touches any state
+    fn before_command(&self, _command: &CommandInfo<'_>) -> Result<(), CacheError> {
+        Ok(())
+    }
+
+    // `outcome` is the same label tracing records: "ok", "hit", "miss",
+    // "error", "rejected" and so on
+    fn after_command(&self, _command: &CommandInfo<'_>, _outcome: &str, _elapsed: Duration) {}
+}
@@ -0,0 +1,160 @@
+// Redis-style glob matching: `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` escapes.
+// Walks both strings by byte offset, so matching allocates nothing.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Offsets to resume from when the most recent `*` needs to swallow one more char
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while let Some(c) = text[t..].chars().next() {
+        let mut rest = pattern[p..].chars();
+        // How many pattern bytes the step consumed, if `c` matched
+        let step = match rest.next() {
+            Some('*') => {
+                p = pattern.len() - pattern[p..].trim_start_matches('*').len();
+                if p == pattern.len() {
+                    return true;
+                }
+                backtrack = Some((p, t));
+                continue;
+            },
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], c),
+            Some('\\') => match rest.next() {
+                Some(escaped) => (escaped == c).then_some(1 + escaped.len_utf8()),
+                None => ('\\' == c).then_some(1),
+            },
+            Some(pc) => (pc == c).then_some(pc.len_utf8()),
+            None => None,
+        };
+
+        match (step, backtrack) {
+            (Some(consumed), _) => {
+                p += consumed;
+                t += c.len_utf8();
+            },
+            (None, Some((star_p, star_t))) => {
+                let skipped = text[star_t..].chars().next().map_or(1, char::len_utf8);
+                p = star_p;
+                t = star_t + skipped;
+                backtrack = Some((star_p, t));
+            },
+            (None, None) => return false,
+        }
+    }
+
+    pattern[p..].chars().all(|c| c == '*')
+}
+
+// Returns how many pattern bytes the class spans when it matches `c`
+fn match_class(pattern: &str, c: char) -> Option<usize> {
+    let mut rest = pattern[1..].chars();
+    let negated = rest.clone().next() == Some('^');
+    if negated {
+        rest.next();
+    }
+
+    let mut matched = false;
+    loop {
+        let mut ahead = rest.clone();
+        match ahead.next() {
+            // An unterminated class runs to the end of the pattern, as in Redis
+            None => break,
+            Some(']') => {
+                rest = ahead;
+                break;
+            },
+            Some('\\') if ahead.clone().next().is_some() => {
+                matched |= ahead.next() == Some(c);
+            },
+            Some(lo) => {
+                let mut range = ahead.clone();
+                match (range.next(), range.next()) {
+                    (Some('-'), Some(hi)) if hi != ']' => {
+                        matched |= (lo.min(hi)..=lo.max(hi)).contains(&c);
+                        ahead = range;
+                    },
+                    _ => matched |= lo == c,
+                }
+            },
+        }
+        rest = ahead;
+    }
+
+    let consumed = pattern.len() - rest.as_str().len();
+    (matched != negated).then_some(consumed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob_match("user:*", "user:42"));
+        assert!(glob_match("user:*", "user:"));
+        assert!(glob_match("*:42", "user:42"));
+        assert!(glob_match("u*r:*2", "user:42"));
+        assert!(glob_match("a**b", "ab"));
+        assert!(!glob_match("user:*", "session:42"));
+        assert!(!glob_match("*:42", "user:43"));
+    }
+
+    #[test]
+    fn trailing_star_matches_the_rest() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("cache*", "cache"));
+        assert!(glob_match("cache***", "cache:entry"));
+        assert!(!glob_match("cache*", "cach"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_char() {
+        assert!(glob_match("h?llo", "hello"));
+        assert!(glob_match("h?llo", "hällo"));
+        assert!(!glob_match("h?llo", "hllo"));
+        assert!(!glob_match("h?llo", "heello"));
+    }
+
+    #[test]
+    fn classes_and_ranges() {
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[ae]llo", "hillo"));
+        assert!(glob_match("key[a-z]", "keym"));
+        assert!(!glob_match("key[a-z]", "keyM"));
+        assert!(glob_match("key[z-a]", "keym"));
+        assert!(glob_match("v[0-9][0-9]", "v42"));
+        // An unterminated class runs to the end of the pattern
+        assert!(glob_match("key[ab", "keyb"));
+    }
+
+    #[test]
+    fn negated_classes() {
+        assert!(glob_match("h[^x]llo", "hello"));
+        assert!(!glob_match("h[^x]llo", "hxllo"));
+        assert!(!glob_match("key[^a-z]", "keyq"));
+        assert!(glob_match("key[^a-z]", "key7"));
+    }
+
+    #[test]
+    fn escapes_match_literally() {
+        assert!(glob_match(r"price\*", "price*"));
+        assert!(!glob_match(r"price\*", "price:10"));
+        assert!(glob_match(r"what\?", "what?"));
+        assert!(!glob_match(r"what\?", "whats"));
+        assert!(glob_match(r"\[tag\]", "[tag]"));
+        assert!(glob_match(r"[\]]", "]"));
+        // A trailing backslash matches itself
+        assert!(glob_match("dir\\", "dir\\"));
+    }
+
+    #[test]
+    fn empty_pattern_and_subject() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "key"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("[a-z]", ""));
+        assert!(!glob_match("key", ""));
+    }
+}
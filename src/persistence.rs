@@ -0,0 +1,135 @@
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::delta::wall_clock_micros;
+use crate::error::CacheError;
+use crate::types::{CacheEntry, EntryMap, ExpirationEntry};
+
+const SNAPSHOT_HEADER: &str = "MINIREDIS-SNAPSHOT 1";
+
+// Each record is a `<key_len> <value_len> <ttl_ms|->` line followed by the raw
+// key and value bytes. TTLs are stored as the time remaining at save time.
+pub(crate) fn save_snapshot(path: &Path, data: &EntryMap) -> Result<(), CacheError> {
+    let tmp_path = path.with_extension("tmp");
+    let now = Instant::now();
+
+    let file = File::create(&tmp_path).map_err(persistence_error)?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "{}", SNAPSHOT_HEADER).map_err(persistence_error)?;
+
+    for (key, entry) in data {
+        let ttl = match entry.expiration {
+            Some(exp) if exp <= now => continue,
+            Some(exp) => exp.duration_since(now).as_millis().to_string(),
+            None => "-".to_string(),
+        };
+        writeln!(writer, "{} {} {}", key.len(), entry.value.len(), ttl).map_err(persistence_error)?;
+        writer.write_all(key.as_bytes()).map_err(persistence_error)?;
+        writer.write_all(entry.value.as_bytes()).map_err(persistence_error)?;
+        writeln!(writer).map_err(persistence_error)?;
+    }
+
+    writer.flush().map_err(persistence_error)?;
+    writer.get_ref().sync_all().map_err(persistence_error)?;
+    fs::rename(&tmp_path, path).map_err(persistence_error)
+}
+
+pub(crate) fn load_snapshot(
+    path: &Path,
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+) -> Result<(), CacheError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(persistence_error(e)),
+    };
+    read_snapshot(BufReader::new(file), data, expiration_queue)
+}
+
+// Unlike load_snapshot, a missing file is an error
+pub(crate) fn read_snapshot_file(path: &Path, data: &mut EntryMap) -> Result<(), CacheError> {
+    let file = File::open(path).map_err(persistence_error)?;
+    read_snapshot(BufReader::new(file), data, &mut BinaryHeap::new())
+}
+
+// Everything after opening the file, split out so the fuzz targets can feed
+// arbitrary bytes through it
+pub(crate) fn read_snapshot(
+    mut reader: impl BufRead,
+    data: &mut EntryMap,
+    expiration_queue: &mut BinaryHeap<ExpirationEntry>,
+) -> Result<(), CacheError> {
+    let now = Instant::now();
+
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(persistence_error)?;
+    if line.trim_end() != SNAPSHOT_HEADER {
+        return Err(CacheError::SerializationError("unrecognized snapshot header".to_string()));
+    }
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(persistence_error)? == 0 {
+            return Ok(());
+        }
+
+        let mut fields = line.split_whitespace();
+        let key_len = parse_field(fields.next())?;
+        let value_len = parse_field(fields.next())?;
+        let expiration = match fields.next() {
+            Some("-") => None,
+            ttl => Some(now.checked_add(Duration::from_millis(parse_field(ttl)? as u64)).ok_or_else(malformed_record)?),
+        };
+
+        let key = read_string(&mut reader, key_len)?;
+        let value = read_string(&mut reader, value_len)?;
+        let mut newline = [0u8; 1];
+        reader.read_exact(&mut newline).map_err(persistence_error)?;
+
+        if let Some(exp) = expiration {
+            expiration_queue.push(ExpirationEntry {
+                expiration: exp,
+                key: key.clone(),
+            });
+        }
+
+        data.insert(key, CacheEntry {
+            value: value.into(),
+            expiration,
+            inserted_at: now,
+            last_accessed: now,
+            access_count: 0,
+            written_at: wall_clock_micros(),
+            checksum: None,
+        });
+    }
+}
+
+fn parse_field(field: Option<&str>) -> Result<usize, CacheError> {
+    field
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(malformed_record)
+}
+
+fn malformed_record() -> CacheError {
+    CacheError::SerializationError("malformed snapshot record".to_string())
+}
+
+// Grows the buffer as bytes arrive rather than trusting the length up front,
+// so a corrupted length fails as a short read instead of a huge allocation
+fn read_string(reader: &mut impl Read, len: usize) -> Result<String, CacheError> {
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf).map_err(persistence_error)?;
+    if buf.len() < len {
+        return Err(persistence_error(ErrorKind::UnexpectedEof.into()));
+    }
+    String::from_utf8(buf).map_err(|e| CacheError::SerializationError(e.to_string()))
+}
+
+fn persistence_error(e: std::io::Error) -> CacheError {
+    CacheError::PersistenceError(e.to_string())
+}
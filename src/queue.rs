@@ -0,0 +1,128 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::cache::Cache;
+use crate::error::CacheError;
+
+// Messages waiting for a consumer, plus the ones handed out and not yet
+// acknowledged. Each delivery gets a fresh receipt, and its visibility
+// deadline goes on a heap like the cache's expiration queue, so lapsed
+// deliveries are found from the top without scanning everything in flight.
+// A receipt that was acked, or whose message was requeued, leaves a stale
+// heap entry that is skipped when it comes up. Receipts come from a counter
+// owned by the event loop, so a queue that is dropped when empty and later
+// recreated never hands out a receipt an old consumer still holds. Delayed
+// messages wait on a second heap, ordered by when they become visible and
+// then by when they were enqueued, and join the back of the queue once due.
+#[derive(Debug, Default)]
+pub(crate) struct Queue {
+    ready: VecDeque<String>,
+    in_flight: HashMap<u64, String>,
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+    delayed: BinaryHeap<Reverse<(Instant, u64, String)>>,
+    next_delayed: u64,
+}
+
+impl Queue {
+    pub fn push(&mut self, body: String) {
+        self.ready.push_back(body);
+    }
+
+    pub fn push_at(&mut self, body: String, visible_at: Instant) {
+        self.delayed.push(Reverse((visible_at, self.next_delayed, body)));
+        self.next_delayed += 1;
+    }
+
+    pub fn pop(&mut self, visibility: Duration, now: Instant, next_receipt: &mut u64) -> Option<(u64, String)> {
+        self.requeue_lapsed(now);
+        let body = self.ready.pop_front()?;
+        let receipt = *next_receipt;
+        *next_receipt += 1;
+        self.in_flight.insert(receipt, body.clone());
+        self.deadlines.push(Reverse((now + visibility, receipt)));
+        Some((receipt, body))
+    }
+
+    pub fn ack(&mut self, receipt: u64) -> bool {
+        self.in_flight.remove(&receipt).is_some()
+    }
+
+    // Deliveries whose visibility timeout has passed go to the back of the
+    // queue, to be handed out again under a new receipt, and delayed messages
+    // that are due join them
+    pub fn requeue_lapsed(&mut self, now: Instant) {
+        while self.delayed.peek().is_some_and(|Reverse((visible_at, _, _))| *visible_at <= now) {
+            if let Some(Reverse((_, _, body))) = self.delayed.pop() {
+                self.ready.push_back(body);
+            }
+        }
+        while let Some(Reverse((deadline, receipt))) = self.deadlines.peek().copied() {
+            if deadline > now {
+                break;
+            }
+            self.deadlines.pop();
+            if let Some(body) = self.in_flight.remove(&receipt) {
+                self.ready.push_back(body);
+            }
+        }
+    }
+
+    pub fn len(&self) -> (usize, usize) {
+        (self.ready.len(), self.in_flight.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty() && self.in_flight.is_empty() && self.delayed.is_empty()
+    }
+}
+
+// A named work queue kept on the cache's event loop, in the style of SQS: a
+// dequeued message stays invisible to other consumers for its visibility
+// timeout and is delivered again if it isn't acked by then. Delivery is
+// therefore at least once. Queues live beside the cached data: they have no
+// TTL, are not evicted or persisted, and are cleared by flush.
+pub struct MessageQueue<'a> {
+    cache: &'a Cache,
+    name: String,
+}
+
+impl<'a> MessageQueue<'a> {
+    // The queue is created by the first enqueue and dropped once it is empty
+    pub fn new(cache: &'a Cache, name: impl Into<String>) -> Self {
+        MessageQueue { cache, name: name.into() }
+    }
+
+    pub fn enqueue(&self, body: String) -> Result<(), CacheError> {
+        self.cache.queue_push(&self.name, body, Duration::ZERO)
+    }
+
+    // The message stays invisible to dequeuers until `deliver_after` has
+    // passed, then joins the back of the queue, e.g. to retry a failed job
+    // with backoff
+    pub fn enqueue_delayed(&self, body: String, deliver_after: Duration) -> Result<(), CacheError> {
+        self.cache.queue_push(&self.name, body, deliver_after)
+    }
+
+    // The oldest visible message with its receipt, or None if there is none.
+    // Ack the receipt once the message is handled.
+    pub fn dequeue_with_visibility(&self, timeout: Duration) -> Result<Option<(u64, String)>, CacheError> {
+        if timeout.is_zero() {
+            return Err(CacheError::ConfigError("visibility timeout must be non-zero".to_string()));
+        }
+        self.cache.queue_pop(&self.name, timeout)
+    }
+
+    // False if the receipt is unknown, already acked, or lapsed, in which case
+    // the message has been or will be delivered again
+    pub fn ack(&self, receipt: u64) -> Result<bool, CacheError> {
+        self.cache.queue_ack(&self.name, receipt)
+    }
+
+    // Messages waiting to be delivered, and messages delivered but not yet
+    // acked, lapsed ones counted as waiting. Delayed messages are counted
+    // once they are due.
+    pub fn len(&self) -> Result<(usize, usize), CacheError> {
+        self.cache.queue_len(&self.name)
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use crate::cache::Cache;
+use crate::config::CacheConfig;
+use crate::error::CacheError;
+use crate::stats::CacheStats;
+
+// Named, independently configured caches ("sessions", "html", ...) that share
+// one lifecycle. Each cache keeps its own event loop.
+#[derive(Default)]
+pub struct CacheRegistry {
+    caches: BTreeMap<String, Cache>,
+}
+
+impl CacheRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces any cache already registered under `name` and returns it
+    pub fn insert(&mut self, name: impl Into<String>, config: CacheConfig) -> Result<Option<Cache>, CacheError> {
+        Ok(self.caches.insert(name.into(), Cache::try_new(config)?))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Cache> {
+        self.caches.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Cache> {
+        self.caches.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.caches.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Cache)> {
+        self.caches.iter().map(|(name, cache)| (name.as_str(), cache))
+    }
+
+    pub fn len(&self) -> usize {
+        self.caches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.caches.is_empty()
+    }
+
+    pub fn stats(&self, name: &str) -> Option<Result<CacheStats, CacheError>> {
+        self.caches.get(name).map(Cache::get_stats)
+    }
+
+    // Counters and latency histograms summed over every registered cache
+    pub fn aggregate_stats(&self) -> Result<CacheStats, CacheError> {
+        let mut total = CacheStats::default();
+        for cache in self.caches.values() {
+            total.merge(&cache.get_stats()?);
+        }
+        Ok(total)
+    }
+
+    // Signals every event loop first so they wind down (and persist) in
+    // parallel, then waits for each of them, even while clones are still alive
+    pub fn shutdown(self) {
+        for cache in self.caches.values() {
+            cache.request_shutdown();
+        }
+        for cache in self.caches.values() {
+            cache.join();
+        }
+    }
+}
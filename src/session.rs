@@ -0,0 +1,152 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::cache::Cache;
+use crate::delta::wall_clock_micros;
+use crate::error::CacheError;
+use crate::types::Expiry;
+
+static TOKENS_ISSUED: AtomicU64 = AtomicU64::new(0);
+
+// Sessions stored as ordinary entries with a sliding TTL: `<prefix>:<token>`
+// holds the owner and the caller's session data, and `<prefix>:user:<user>`
+// lists the user's tokens so sessions_for_user doesn't scan the keyspace.
+// The index is updated with compare-and-swap, so concurrent logins for one
+// user don't lose each other's tokens, and tokens of sessions that expired
+// or were destroyed are dropped from it whenever it is rewritten.
+pub struct SessionStore<'a> {
+    cache: &'a Cache,
+    prefix: String,
+    ttl: Duration,
+}
+
+impl<'a> SessionStore<'a> {
+    // Sessions expire `ttl` after they were last read or touched
+    pub fn new(cache: &'a Cache, prefix: impl Into<String>, ttl: Duration) -> Self {
+        SessionStore { cache, prefix: prefix.into(), ttl }
+    }
+
+    // Returns the new session's token
+    pub fn create_session(&self, user: &str, data: String) -> Result<String, CacheError> {
+        let token = new_token();
+        self.cache.set(self.session_key(&token), encode(user, &data), Some(self.ttl))?;
+        self.update_index(user, Some(&token))?;
+        Ok(token)
+    }
+
+    // The session's data, restarting its TTL
+    pub fn get_session(&self, token: &str) -> Result<Option<String>, CacheError> {
+        let Some(value) = self.cache.get_ex(&self.session_key(token), Expiry::After(self.ttl))? else {
+            return Ok(None);
+        };
+        let (user, data) = decode(&value)?;
+        // The index lives as long as the user's most recently used session
+        self.cache.update_ttl(&self.index_key(user), self.ttl)?;
+        Ok(Some(data.to_string()))
+    }
+
+    // Restarts the session's TTL without returning its data; false if the
+    // session doesn't exist
+    pub fn touch_session(&self, token: &str) -> Result<bool, CacheError> {
+        Ok(self.get_session(token)?.is_some())
+    }
+
+    // False if the session had already expired or been destroyed
+    pub fn destroy_session(&self, token: &str) -> Result<bool, CacheError> {
+        let key = self.session_key(token);
+        let Some(value) = self.cache.get(&key)? else {
+            return Ok(false);
+        };
+        let (user, _) = decode(&value)?;
+        let removed = self.cache.delete(&key)?;
+        self.update_index(user, None)?;
+        Ok(removed)
+    }
+
+    // Tokens of the user's live sessions, in the order they were created
+    pub fn sessions_for_user(&self, user: &str) -> Result<Vec<String>, CacheError> {
+        let Some(index) = self.cache.get(&self.index_key(user))? else {
+            return Ok(Vec::new());
+        };
+        self.live_tokens(&index)
+    }
+
+    // Rewrites the user's index without dead tokens, adding `token` if given.
+    // A failed compare-and-swap means another update of the same index
+    // landed, so retrying until ours lands can't livelock.
+    fn update_index(&self, user: &str, token: Option<&str>) -> Result<(), CacheError> {
+        let key = self.index_key(user);
+        loop {
+            let current = self.cache.get(&key)?;
+            let mut tokens = match &current {
+                Some(index) => self.live_tokens(index)?,
+                None => Vec::new(),
+            };
+            tokens.extend(token.map(str::to_string));
+            let updated = tokens.join(",");
+
+            // An index emptied by the last destroy is kept and expires with
+            // its TTL, so a concurrent login's compare-and-swap still applies
+            let written = match &current {
+                Some(index) => self.cache.compare_and_swap(&key, index, updated)?,
+                None if updated.is_empty() => return Ok(()),
+                None => self.cache.bulk_set_nx([(key.clone(), updated)])?,
+            };
+            if written {
+                self.cache.update_ttl(&key, self.ttl)?;
+                return Ok(());
+            }
+        }
+    }
+
+    fn live_tokens(&self, index: &str) -> Result<Vec<String>, CacheError> {
+        let tokens: Vec<&str> = index.split(',').filter(|token| !token.is_empty()).collect();
+        let sessions = self.cache.bulk_get(tokens.iter().map(|token| self.session_key(token)))?;
+        Ok(tokens.into_iter()
+            .filter(|token| sessions.get(&self.session_key(token)).is_some_and(Option::is_some))
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn session_key(&self, token: &str) -> String {
+        format!("{}:{}", self.prefix, token)
+    }
+
+    fn index_key(&self, user: &str) -> String {
+        format!("{}:user:{}", self.prefix, user)
+    }
+}
+
+// 128 bits of SipHash output under the process's randomly seeded
+// RandomState keys: unpredictable without those keys, though not from a
+// dedicated CSPRNG
+fn new_token() -> String {
+    let issued = TOKENS_ISSUED.fetch_add(1, Ordering::Relaxed);
+    let words: Vec<u64> = (0..2u64)
+        .map(|half| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(issued);
+            hasher.write_u64(half);
+            hasher.write_u64(wall_clock_micros());
+            hasher.finish()
+        })
+        .collect();
+    format!("{:016x}{:016x}", words[0], words[1])
+}
+
+// `<user length>:<user><data>`, so neither part needs escaping
+fn encode(user: &str, data: &str) -> String {
+    format!("{}:{}{}", user.len(), user, data)
+}
+
+fn decode(value: &str) -> Result<(&str, &str), CacheError> {
+    let malformed = || CacheError::SerializationError("malformed session entry".to_string());
+    let (len, rest) = value.split_once(':').ok_or_else(malformed)?;
+    let len: usize = len.parse().map_err(|_| malformed())?;
+    if !rest.is_char_boundary(len) {
+        return Err(malformed());
+    }
+    Ok(rest.split_at(len))
+}
@@ -0,0 +1,65 @@
+#[cfg(feature = "persistence")]
+use std::path::Path;
+use std::time::Instant;
+
+#[cfg(feature = "persistence")]
+use crate::config::HashAlgorithm;
+#[cfg(feature = "persistence")]
+use crate::error::CacheError;
+#[cfg(feature = "persistence")]
+use crate::hashing::KeyHashBuilder;
+#[cfg(feature = "persistence")]
+use crate::persistence;
+use crate::analysis::{self, KeyspaceReport};
+use crate::types::EntryMap;
+
+// A frozen, read-only view of the cache taken by Cache::snapshot(). Reading it
+// never touches the event loop, so long exports and scans don't stall writers.
+pub struct Snapshot {
+    pub(crate) entries: EntryMap,
+    pub(crate) taken_at: Instant,
+}
+
+impl Snapshot {
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key)
+            .filter(|entry| entry.expiration.is_none_or(|exp| exp > self.taken_at))
+            .map(|entry| entry.value.as_str())
+    }
+
+    // Entries that were live when the snapshot was taken, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter()
+            .filter(|(_, entry)| entry.expiration.is_none_or(|exp| exp > self.taken_at))
+            .map(|(key, entry)| (key.as_str(), entry.value.as_str()))
+    }
+
+    // Distributions of value sizes, TTLs and idle times as of when the
+    // snapshot was taken
+    pub fn analyze(&self) -> KeyspaceReport {
+        analysis::analyze(&self.entries, self.taken_at)
+    }
+
+    // Writes the same format the cache reloads from CacheConfig::with_persistence_path
+    #[cfg(feature = "persistence")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CacheError> {
+        persistence::save_snapshot(path.as_ref(), &self.entries)
+    }
+
+    // Reads a file written by save() or by a cache shutting down with a
+    // persistence path, e.g. to warm a fresh cache from it
+    #[cfg(feature = "persistence")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Snapshot, CacheError> {
+        let mut entries = EntryMap::with_hasher(KeyHashBuilder::new(HashAlgorithm::Fast));
+        persistence::read_snapshot_file(path.as_ref(), &mut entries)?;
+        Ok(Snapshot { entries, taken_at: Instant::now() })
+    }
+}
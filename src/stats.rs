@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use crate::ghost::PolicySimulation;
 
 /// created this to represents cache statistics for statistics tracking.
 
@@ -6,6 +9,21 @@ pub struct CacheStats {
     pub(crate) hits: u64,
     pub(crate) misses: u64,
     pub(crate) evictions: u64,
+    pub(crate) deduplicated_bytes: u64,
+    pub(crate) corrupted_entries: u64,
+    pub(crate) negative_hits: u64,
+    pub(crate) dependency_invalidations: u64,
+    pub(crate) timed_out: u64,
+    pub(crate) normalized_keys: u64,
+    pub(crate) repaired_settings: Vec<&'static str>,
+    pub(crate) queue_wait: LatencyHistogram,
+    pub(crate) processing: LatencyHistogram,
+    pub(crate) busy_time: Duration,
+    pub(crate) idle_time: Duration,
+    pub(crate) loop_wakeups: u64,
+    pub(crate) prefixes: Vec<PrefixStats>,
+    pub(crate) policy_simulations: Vec<PolicySimulation>,
+    pub(crate) sizing_curve: Vec<PolicySimulation>,
 }
 
 impl CacheStats {
@@ -22,6 +40,174 @@ impl CacheStats {
         self.evictions // / this here returns the number of evicted entries
     }
 
+    pub fn deduplicated_bytes(&self) -> u64 {
+        self.deduplicated_bytes // / bytes currently saved by sharing identical values
+    }
+
+    pub fn corrupted_entries(&self) -> u64 {
+        self.corrupted_entries // / entries evicted because their value failed its checksum
+    }
+
+    pub fn negative_hits(&self) -> u64 {
+        self.negative_hits // / lookups answered from a cached "not found"
+    }
+
+    pub fn dependency_invalidations(&self) -> u64 {
+        self.dependency_invalidations // / entries dropped because a key they depend on changed
+    }
+
+    pub fn timed_out(&self) -> u64 {
+        self.timed_out // / commands answered with Timeout because they were dequeued past their deadline
+    }
+
+    pub fn normalized_keys(&self) -> u64 {
+        self.normalized_keys // / keys the key normalizer rewrote onto a different canonical key
+    }
+
+    pub fn repaired_settings(&self) -> &[&'static str] {
+        &self.repaired_settings // / settings Cache::new replaced with working fallbacks because validate() rejected them
+    }
+
+    pub fn prefix_stats(&self) -> &[PrefixStats] {
+        &self.prefixes // / counters for each prefix passed to with_tracked_prefixes
+    }
+
+    pub fn policy_simulations(&self) -> &[PolicySimulation] {
+        &self.policy_simulations // / hit rates other eviction policies would have had; see with_policy_simulation
+    }
+
+    pub fn sizing_curve(&self) -> &[PolicySimulation] {
+        &self.sizing_curve // / simulated LRU hit rates at max_size and 10/25/50% above it; see with_sizing_curve
+    }
+
+    // Percentage points of hit rate gained by growing max_size by `growth`
+    // (0.1, 0.25 or 0.5), against the simulated cache at the current size
+    pub fn estimated_gain(&self, growth: f64) -> Option<f64> {
+        let baseline = self.sizing_curve.first()?;
+        let target = (baseline.capacity as f64 * (1.0 + growth)).ceil() as usize;
+        let grown = self.sizing_curve.iter().find(|sim| sim.capacity == target)?;
+        Some(grown.hit_rate() - baseline.hit_rate())
+    }
+
+    pub fn queue_wait(&self) -> &LatencyHistogram {
+        &self.queue_wait // / time commands spent waiting in the event loop's queue
+    }
+
+    pub fn processing_time(&self) -> &LatencyHistogram {
+        &self.processing // / time the event loop spent executing commands
+    }
+
+    pub fn busy_time(&self) -> Duration {
+        self.busy_time // / time the event loop spent working rather than waiting for commands
+    }
+
+    pub fn idle_time(&self) -> Duration {
+        self.idle_time // / time the event loop spent blocked waiting for commands
+    }
+
+    pub fn loop_wakeups(&self) -> u64 {
+        self.loop_wakeups // / how often the event loop woke up after running out of work
+    }
+
+    pub fn utilization(&self) -> f64 {
+        let total = self.busy_time + self.idle_time;
+        if total.is_zero() {
+            0.0
+        } else {
+            (self.busy_time.as_secs_f64() / total.as_secs_f64()) * 100.0 // / share of time spent busy, as a percentage
+        }
+    }
+
+    pub fn commands_per_wakeup(&self) -> f64 {
+        if self.loop_wakeups == 0 {
+            self.processing.count() as f64
+        } else {
+            self.processing.count() as f64 / self.loop_wakeups as f64 // / commands handled per wakeup; grows as the loop falls behind
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.evictions += other.evictions;
+        self.deduplicated_bytes += other.deduplicated_bytes;
+        self.corrupted_entries += other.corrupted_entries;
+        self.negative_hits += other.negative_hits;
+        self.dependency_invalidations += other.dependency_invalidations;
+        self.timed_out += other.timed_out;
+        self.normalized_keys += other.normalized_keys;
+        for setting in &other.repaired_settings {
+            if !self.repaired_settings.contains(setting) {
+                self.repaired_settings.push(setting);
+            }
+        }
+        self.queue_wait.merge(&other.queue_wait);
+        self.processing.merge(&other.processing);
+        self.busy_time += other.busy_time;
+        self.idle_time += other.idle_time;
+        self.loop_wakeups += other.loop_wakeups;
+        for theirs in &other.prefixes {
+            match self.prefixes.iter_mut().find(|ours| ours.prefix == theirs.prefix) {
+                Some(ours) => {
+                    ours.hits += theirs.hits;
+                    ours.misses += theirs.misses;
+                    ours.evictions += theirs.evictions;
+                    ours.normalized_keys += theirs.normalized_keys;
+                },
+                None => self.prefixes.push(theirs.clone()),
+            }
+        }
+    }
+
+    // Keeps the counts of prefixes that are still tracked
+    pub(crate) fn track_prefixes(&mut self, prefixes: &[String]) {
+        let mut previous = std::mem::take(&mut self.prefixes);
+        self.prefixes = prefixes.iter()
+            .map(|prefix| match previous.iter().position(|stats| stats.prefix == *prefix) {
+                Some(index) => previous.swap_remove(index),
+                None => PrefixStats { prefix: prefix.clone(), ..PrefixStats::default() },
+            })
+            .collect();
+    }
+
+    pub(crate) fn record_hit(&mut self, key: &str) {
+        self.hits += 1;
+        if let Some(prefix) = self.prefix_for(key) {
+            prefix.hits += 1;
+        }
+    }
+
+    pub(crate) fn record_miss(&mut self, key: &str) {
+        self.misses += 1;
+        if let Some(prefix) = self.prefix_for(key) {
+            prefix.misses += 1;
+        }
+    }
+
+    pub(crate) fn record_eviction(&mut self, key: &str) {
+        self.evictions += 1;
+        if let Some(prefix) = self.prefix_for(key) {
+            prefix.evictions += 1;
+        }
+    }
+
+    // A raw key folded onto `canonical` by the key normalizer. Its hits and
+    // misses are recorded under `canonical` as usual, so this is what tells
+    // how much of a prefix's traffic arrived under other spellings.
+    pub(crate) fn record_normalized(&mut self, canonical: &str) {
+        self.normalized_keys += 1;
+        if let Some(prefix) = self.prefix_for(canonical) {
+            prefix.normalized_keys += 1;
+        }
+    }
+
+    // The longest tracked prefix of `key`, so each key counts toward one prefix
+    fn prefix_for(&mut self, key: &str) -> Option<&mut PrefixStats> {
+        self.prefixes.iter_mut()
+            .filter(|stats| key.starts_with(stats.prefix.as_str()))
+            .max_by_key(|stats| stats.prefix.len())
+    }
+
     pub fn hit_rate(&self) -> f64 {
         let total = self.hits + self.misses;
         if total == 0 {
@@ -30,4 +216,104 @@ impl CacheStats {
             (self.hits as f64 / total as f64) * 100.0 // / this here returns the hit rate as a percentage
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PrefixStats {
+    pub(crate) prefix: String,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) evictions: u64,
+    pub(crate) normalized_keys: u64,
+}
+
+impl PrefixStats {
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    pub fn normalized_keys(&self) -> u64 {
+        self.normalized_keys
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            (self.hits as f64 / total as f64) * 100.0
+        }
+    }
+}
+
+const LATENCY_BUCKETS: usize = 32;
+
+/// Power-of-two microsecond buckets: bucket `i` counts samples below `2^i` µs.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS],
+    count: u64,
+    total_micros: u64,
+    max_micros: u64,
+}
+
+impl LatencyHistogram {
+    pub(crate) fn record(&mut self, duration: Duration) {
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = ((u64::BITS - micros.leading_zeros()) as usize).min(LATENCY_BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total_micros = self.total_micros.saturating_add(micros);
+        self.max_micros = self.max_micros.max(micros);
+    }
+
+    pub(crate) fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.total_micros = self.total_micros.saturating_add(other.total_micros);
+        self.max_micros = self.max_micros.max(other.max_micros);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_micros(self.total_micros.checked_div(self.count).unwrap_or(0))
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_micros)
+    }
+
+    // Upper bound of the bucket containing the requested percentile (0-100)
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let target = ((percentile.clamp(0.0, 100.0) / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Duration::from_micros(1u64 << bucket).min(self.max());
+            }
+        }
+        self.max()
+    }
+}
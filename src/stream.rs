@@ -0,0 +1,400 @@
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{BufRead, Read};
+use std::sync::Arc;
+
+use crate::delta::Delta;
+use crate::error::CacheError;
+
+const CHUNK_HEADER: &str = "MINIREDIS-CHUNK 1";
+
+// A point-in-time copy of the whole cache, encoded once as a delta and served
+// in chunks: to bootstrap a follower (merge_delta, then delta_since from the
+// stream's watermark) or to back the cache up remotely. Any chunk can be asked
+// for again by offset, so a transfer that drops resumes where it stopped.
+// Cheap to clone; keep it around for as long as clients may resume.
+#[derive(Clone)]
+pub struct SnapshotStream {
+    id: u64,
+    watermark: u64,
+    bytes: Arc<[u8]>,
+    chunk_size: usize,
+}
+
+// One piece of a SnapshotStream, with what the receiver needs to check it
+// belongs to the stream it started on and arrived intact
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamChunk {
+    pub(crate) stream_id: u64,
+    pub(crate) offset: usize,
+    pub(crate) total_len: usize,
+    pub(crate) checksum: u32,
+    pub(crate) bytes: Vec<u8>,
+}
+
+// Reassembles a SnapshotStream on the other side. Chunks must arrive in
+// order; resume_offset() says which one to ask for next.
+#[derive(Debug, Default)]
+pub struct SnapshotReceiver {
+    stream_id: Option<u64>,
+    total_len: usize,
+    bytes: Vec<u8>,
+}
+
+impl SnapshotStream {
+    // A chunk_size of 0 is treated as 1
+    pub(crate) fn new(delta: Delta, chunk_size: usize) -> Self {
+        let bytes: Arc<[u8]> = delta.encode().into();
+        SnapshotStream {
+            id: stream_id(&bytes),
+            watermark: delta.watermark(),
+            bytes,
+            chunk_size: chunk_size.max(1),
+        }
+    }
+
+    // Derived from the contents, so a stream rebuilt from an unchanged cache
+    // accepts resumes meant for the old one and a changed cache doesn't
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Pass to delta_since on the follower's source once the stream is applied
+    pub fn watermark(&self) -> u64 {
+        self.watermark
+    }
+
+    // Total encoded bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.bytes.len().div_ceil(self.chunk_size)
+    }
+
+    // The chunk starting at `offset`, or None once past the end
+    pub fn chunk(&self, offset: usize) -> Option<StreamChunk> {
+        if offset >= self.bytes.len() {
+            return None;
+        }
+        let end = offset.saturating_add(self.chunk_size).min(self.bytes.len());
+        let bytes = self.bytes[offset..end].to_vec();
+        Some(StreamChunk {
+            stream_id: self.id,
+            offset,
+            total_len: self.bytes.len(),
+            checksum: chunk_checksum(&bytes),
+            bytes,
+        })
+    }
+
+    // Every chunk from `offset` on, in order
+    pub fn chunks_from(&self, offset: usize) -> impl Iterator<Item = StreamChunk> + '_ {
+        let mut next = offset;
+        std::iter::from_fn(move || {
+            let chunk = self.chunk(next)?;
+            next += chunk.bytes.len();
+            Some(chunk)
+        })
+    }
+}
+
+impl StreamChunk {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.offset + self.bytes.len() == self.total_len
+    }
+
+    // A `<stream_id> <offset> <total_len> <checksum> <len>` line after the
+    // header, then the raw bytes
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = format!(
+            "{}\n{} {} {} {} {}\n",
+            CHUNK_HEADER, self.stream_id, self.offset, self.total_len, self.checksum, self.bytes.len()
+        )
+        .into_bytes();
+        out.extend_from_slice(&self.bytes);
+        out
+    }
+
+    // Checks the framing only; SnapshotReceiver::receive checks the checksum
+    pub fn decode(mut bytes: &[u8]) -> Result<StreamChunk, CacheError> {
+        let mut line = String::new();
+        bytes.read_line(&mut line).map_err(malformed)?;
+        if line.trim_end() != CHUNK_HEADER {
+            return Err(CacheError::SerializationError("unrecognized chunk header".to_string()));
+        }
+        line.clear();
+        bytes.read_line(&mut line).map_err(malformed)?;
+
+        let mut fields = line.split_whitespace();
+        let stream_id = parse_field(fields.next())?;
+        let offset = parse_field(fields.next())? as usize;
+        let total_len = parse_field(fields.next())? as usize;
+        let checksum = u32::try_from(parse_field(fields.next())?).map_err(|_| malformed_chunk())?;
+        let len = parse_field(fields.next())? as usize;
+        if offset.checked_add(len).is_none_or(|end| end > total_len) {
+            return Err(malformed_chunk());
+        }
+
+        // Read only what arrived rather than trusting the length up front
+        let mut chunk = Vec::new();
+        (&mut bytes).take(len as u64).read_to_end(&mut chunk).map_err(malformed)?;
+        if chunk.len() < len || !bytes.is_empty() {
+            return Err(malformed_chunk());
+        }
+        Ok(StreamChunk { stream_id, offset, total_len, checksum, bytes: chunk })
+    }
+}
+
+impl SnapshotReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Where the next chunk should start; ask the stream for this after a drop
+    pub fn resume_offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.stream_id.is_some() && self.bytes.len() == self.total_len
+    }
+
+    // A chunk already received is ignored, so resending after an unclear
+    // failure is safe. Anything else out of place is an error and leaves the
+    // receiver as it was.
+    pub fn receive(&mut self, chunk: StreamChunk) -> Result<(), CacheError> {
+        if let Some(stream_id) = self.stream_id {
+            if chunk.stream_id != stream_id || chunk.total_len != self.total_len {
+                return Err(CacheError::SerializationError("chunk belongs to a different snapshot stream".to_string()));
+            }
+        }
+        if chunk_checksum(&chunk.bytes) != chunk.checksum {
+            return Err(CacheError::SerializationError(format!("chunk at offset {} failed its checksum", chunk.offset)));
+        }
+        if chunk.offset + chunk.bytes.len() <= self.bytes.len() {
+            return Ok(());
+        }
+        if chunk.offset != self.bytes.len() {
+            return Err(CacheError::SerializationError(format!(
+                "expected the chunk at offset {}, got {}",
+                self.bytes.len(),
+                chunk.offset
+            )));
+        }
+
+        let stream_len = self.bytes.len() + chunk.bytes.len();
+        if stream_len == chunk.total_len && stream_id_of(&self.bytes, &chunk.bytes) != chunk.stream_id {
+            return Err(CacheError::SerializationError("reassembled snapshot stream failed its checksum".to_string()));
+        }
+        self.stream_id = Some(chunk.stream_id);
+        self.total_len = chunk.total_len;
+        self.bytes.extend_from_slice(&chunk.bytes);
+        Ok(())
+    }
+
+    // The raw stream, e.g. to keep as a backup; Delta::decode reads it back
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // Hand the result to merge_delta
+    pub fn into_delta(self) -> Result<Delta, CacheError> {
+        if !self.is_complete() {
+            return Err(CacheError::SerializationError(format!(
+                "snapshot stream incomplete: {} of {} bytes",
+                self.bytes.len(),
+                self.total_len
+            )));
+        }
+        Delta::decode(&self.bytes)
+    }
+}
+
+// The same SipHash as value checksums, over the whole stream; doubles as the
+// end-to-end check that the reassembled bytes are the ones that were sent
+fn stream_id(bytes: &[u8]) -> u64 {
+    stream_id_of(bytes, &[])
+}
+
+fn stream_id_of(head: &[u8], tail: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(head);
+    hasher.write(tail);
+    hasher.finish()
+}
+
+fn chunk_checksum(bytes: &[u8]) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish() as u32
+}
+
+fn parse_field(field: Option<&str>) -> Result<u64, CacheError> {
+    field
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(malformed_chunk)
+}
+
+fn malformed_chunk() -> CacheError {
+    CacheError::SerializationError("malformed stream chunk".to_string())
+}
+
+fn malformed(e: std::io::Error) -> CacheError {
+    CacheError::SerializationError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::delta::DeltaEntry;
+
+    fn stream(values: &[&str], chunk_size: usize) -> SnapshotStream {
+        let entries = values.iter().enumerate()
+            .map(|(i, value)| DeltaEntry {
+                key: format!("key:{}", i),
+                value: value.to_string(),
+                ttl: (i % 2 == 0).then(|| Duration::from_secs(60)),
+                written_at: 1_000 + i as u64,
+            })
+            .collect();
+        SnapshotStream::new(Delta { entries, watermark: 2_000 }, chunk_size)
+    }
+
+    fn rejected(result: Result<(), CacheError>) -> bool {
+        matches!(result, Err(CacheError::SerializationError(_)))
+    }
+
+    #[test]
+    fn chunks_reassemble_into_the_original_delta() {
+        let source = stream(&["alpha", "beta", "gamma", ""], 7);
+        assert!(source.chunk_count() > 3);
+
+        let mut receiver = SnapshotReceiver::new();
+        for chunk in source.chunks_from(0) {
+            assert!(!receiver.is_complete());
+            let wire = StreamChunk::decode(&chunk.encode()).unwrap();
+            assert_eq!(wire, chunk);
+            receiver.receive(wire).unwrap();
+        }
+        assert!(receiver.is_complete());
+        assert_eq!(receiver.resume_offset(), source.len());
+
+        let delta = receiver.into_delta().unwrap();
+        assert_eq!(delta.watermark(), source.watermark());
+        let values: Vec<_> = delta.entries.iter().map(|entry| (entry.key.as_str(), entry.value.as_str())).collect();
+        assert_eq!(values, [("key:0", "alpha"), ("key:1", "beta"), ("key:2", "gamma"), ("key:3", "")]);
+    }
+
+    #[test]
+    fn out_of_order_and_missing_chunks_are_rejected() {
+        let source = stream(&["alpha", "beta", "gamma"], 8);
+        let chunks: Vec<_> = source.chunks_from(0).collect();
+        let mut receiver = SnapshotReceiver::new();
+
+        // Nothing received yet, so only offset 0 is acceptable
+        assert!(rejected(receiver.receive(chunks[1].clone())));
+        assert_eq!(receiver.resume_offset(), 0);
+
+        receiver.receive(chunks[0].clone()).unwrap();
+        // Skipping a chunk leaves a gap
+        assert!(rejected(receiver.receive(chunks[2].clone())));
+        assert_eq!(receiver.resume_offset(), chunks[1].offset());
+
+        // Incomplete streams don't turn into deltas
+        let partial = SnapshotReceiver { stream_id: receiver.stream_id, total_len: receiver.total_len, bytes: receiver.bytes.clone() };
+        assert!(partial.into_delta().is_err());
+
+        for chunk in &chunks[1..] {
+            receiver.receive(chunk.clone()).unwrap();
+        }
+        assert!(receiver.is_complete());
+    }
+
+    #[test]
+    fn duplicate_chunks_are_ignored() {
+        let source = stream(&["alpha", "beta"], 8);
+        let chunks: Vec<_> = source.chunks_from(0).collect();
+        let mut receiver = SnapshotReceiver::new();
+
+        receiver.receive(chunks[0].clone()).unwrap();
+        receiver.receive(chunks[1].clone()).unwrap();
+        let offset = receiver.resume_offset();
+        receiver.receive(chunks[0].clone()).unwrap();
+        receiver.receive(chunks[1].clone()).unwrap();
+        assert_eq!(receiver.resume_offset(), offset);
+
+        for chunk in &chunks[2..] {
+            receiver.receive(chunk.clone()).unwrap();
+        }
+        assert_eq!(receiver.into_delta().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn corrupted_chunks_fail_their_checksum() {
+        let source = stream(&["alpha", "beta"], 8);
+        let mut receiver = SnapshotReceiver::new();
+
+        let mut corrupted = source.chunk(0).unwrap();
+        corrupted.bytes[0] ^= 1;
+        assert!(rejected(receiver.receive(corrupted)));
+        assert_eq!(receiver.resume_offset(), 0);
+        receiver.receive(source.chunk(0).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn chunks_from_another_stream_are_rejected() {
+        let source = stream(&["alpha", "beta"], 8);
+        let other = stream(&["alpha", "bets"], 8);
+        assert_ne!(source.id(), other.id());
+
+        let mut receiver = SnapshotReceiver::new();
+        receiver.receive(source.chunk(0).unwrap()).unwrap();
+        let next = receiver.resume_offset();
+        assert!(rejected(receiver.receive(other.chunk(next).unwrap())));
+
+        // A chunk relabelled with the right id still has the wrong length
+        let mut relabelled = stream(&["alpha", "beta, longer"], 8).chunk(next).unwrap();
+        relabelled.stream_id = source.id();
+        assert!(rejected(receiver.receive(relabelled)));
+        assert_eq!(receiver.resume_offset(), next);
+    }
+
+    #[test]
+    fn reassembled_bytes_must_match_the_stream_id() {
+        // Every chunk passes its own checksum and agrees on a forged id, so
+        // only the end-to-end check over the whole stream catches it
+        let source = stream(&["alpha", "beta"], 8);
+        let mut receiver = SnapshotReceiver::new();
+        let chunks: Vec<_> = source.chunks_from(0)
+            .map(|mut chunk| {
+                chunk.stream_id = source.id() ^ 1;
+                chunk
+            })
+            .collect();
+        let (last, rest) = chunks.split_last().unwrap();
+        for chunk in rest {
+            receiver.receive(chunk.clone()).unwrap();
+        }
+        assert!(rejected(receiver.receive(last.clone())));
+        assert!(!receiver.is_complete());
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use crate::cache::Cache;
+use crate::delta::wall_clock_micros;
+use crate::error::CacheError;
+
+// Counts events into fixed wall-clock buckets stored as ordinary counters
+// under `<name>:<bucket start in unix ms>`. Each bucket expires once it falls
+// out of the retention window, so old buckets clean themselves up.
+pub struct TimeSeriesCounter<'a> {
+    cache: &'a Cache,
+    name: String,
+    bucket_ms: u64,
+    retention: Duration,
+}
+
+impl<'a> TimeSeriesCounter<'a> {
+    // Buckets are whole milliseconds, so `bucket` is rounded down and must be
+    // at least 1ms
+    pub fn new(cache: &'a Cache, name: impl Into<String>, bucket: Duration, retention: Duration) -> Result<Self, CacheError> {
+        let bucket_ms = bucket.as_millis() as u64;
+        if bucket_ms == 0 {
+            return Err(CacheError::ConfigError("time series buckets must be at least 1ms wide".to_string()));
+        }
+        Ok(TimeSeriesCounter { cache, name: name.into(), bucket_ms, retention })
+    }
+
+    // Adds `by` to the current bucket and returns the bucket's new total
+    pub fn incr(&self, by: f64) -> Result<f64, CacheError> {
+        let key = self.bucket_key(self.current_bucket());
+        self.cache.incr_by_float_with_ttl(&key, by, self.retention + Duration::from_millis(self.bucket_ms))
+    }
+
+    // Per-bucket totals covering the last `range`, oldest first, as
+    // (bucket start in unix ms, total). The current bucket is included and
+    // the range is capped at the retention window.
+    pub fn buckets(&self, range: Duration) -> Result<Vec<(u64, f64)>, CacheError> {
+        let range_ms = range.min(self.retention).as_millis() as u64;
+        let count = range_ms.div_ceil(self.bucket_ms).max(1);
+        let current = self.current_bucket();
+        let starts: Vec<u64> = (0..count)
+            .rev()
+            .filter_map(|back| current.checked_sub(back * self.bucket_ms))
+            .collect();
+
+        let values = self.cache.bulk_get(starts.iter().map(|start| self.bucket_key(*start)))?;
+        Ok(starts.into_iter()
+            .map(|start| {
+                let total = values.get(&self.bucket_key(start))
+                    .and_then(|value| value.as_deref())
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0.0);
+                (start, total)
+            })
+            .collect())
+    }
+
+    pub fn sum(&self, range: Duration) -> Result<f64, CacheError> {
+        Ok(self.buckets(range)?.iter().map(|(_, total)| total).sum())
+    }
+
+    fn current_bucket(&self) -> u64 {
+        let now_ms = wall_clock_micros() / 1000;
+        now_ms - now_ms % self.bucket_ms
+    }
+
+    fn bucket_key(&self, start: u64) -> String {
+        format!("{}:{}", self.name, start)
+    }
+}
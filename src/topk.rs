@@ -0,0 +1,93 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// Approximate heavy hitters in fixed memory: a count-min sketch estimates
+// each item's frequency, and only the k items with the highest estimates
+// are remembered by name. Estimates can overcount, never undercount.
+#[derive(Debug)]
+pub(crate) struct TopK {
+    k: usize,
+    width: usize,
+    counters: Vec<u64>,
+    top: Vec<(String, u64)>,
+}
+
+impl TopK {
+    pub fn new(k: usize, width: usize, depth: usize) -> Self {
+        TopK { k, width, counters: vec![0; width * depth], top: Vec::with_capacity(k) }
+    }
+
+    pub fn add(&mut self, item: &str) {
+        let mut estimate = u64::MAX;
+        for row in 0..self.counters.len() / self.width {
+            let counter = &mut self.counters[row * self.width + column(item, row, self.width)];
+            *counter += 1;
+            estimate = estimate.min(*counter);
+        }
+
+        if let Some(tracked) = self.top.iter_mut().find(|(name, _)| name == item) {
+            tracked.1 = estimate;
+        } else if self.top.len() < self.k {
+            self.top.push((item.to_string(), estimate));
+        } else if let Some(smallest) = self.top.iter_mut().min_by_key(|(_, count)| *count) {
+            if estimate > smallest.1 {
+                *smallest = (item.to_string(), estimate);
+            }
+        }
+    }
+
+    // Highest estimate first
+    pub fn list(&self) -> Vec<(String, u64)> {
+        let mut top = self.top.clone();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top
+    }
+}
+
+fn column(item: &str, row: usize, width: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    row.hash(&mut hasher);
+    item.hash(&mut hasher);
+    (hasher.finish() % width as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopK;
+
+    #[test]
+    fn heavy_hitter_ranks_first_among_many_light_keys() {
+        let mut sketch = TopK::new(5, 512, 4);
+        // 10,000 keys seen once each, far more than the sketch has columns,
+        // with one key seen 1,000 times spread through them
+        for i in 0..10_000 {
+            sketch.add(&format!("light:{}", i));
+            if i % 10 == 0 {
+                sketch.add("heavy");
+            }
+        }
+
+        let top = sketch.list();
+        assert_eq!(top.len(), 5);
+        let (name, count) = &top[0];
+        assert_eq!(name, "heavy");
+        // Collisions only ever add to a counter
+        assert!(*count >= 1_000, "heavy key undercounted at {}", count);
+        assert!(top[1..].iter().all(|(_, light)| light < count));
+    }
+
+    #[test]
+    fn late_heavy_hitter_displaces_light_keys() {
+        let mut sketch = TopK::new(3, 512, 4);
+        for i in 0..2_000 {
+            sketch.add(&format!("light:{}", i));
+        }
+        for _ in 0..200 {
+            sketch.add("heavy");
+        }
+
+        let top = sketch.list();
+        assert_eq!(top[0].0, "heavy");
+        assert!(top[0].1 >= 200);
+    }
+}
@@ -0,0 +1,20 @@
+// Channels between callers and the event loop: std::sync::mpsc by default, or
+// crossbeam-channel with the `crossbeam` feature.
+
+#[cfg(feature = "crossbeam")]
+pub(crate) use crossbeam_channel::{unbounded as channel, Receiver, RecvTimeoutError, Sender};
+
+#[cfg(not(feature = "crossbeam"))]
+pub(crate) use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+
+// Each request gets exactly one reply, so crossbeam's single-slot array channel
+// is enough and cheaper to set up than its block-list unbounded channel.
+#[cfg(feature = "crossbeam")]
+pub(crate) fn response_channel<T>() -> (Sender<T>, Receiver<T>) {
+    crossbeam_channel::bounded(1)
+}
+
+#[cfg(not(feature = "crossbeam"))]
+pub(crate) fn response_channel<T>() -> (Sender<T>, Receiver<T>) {
+    channel()
+}
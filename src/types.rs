@@ -1,6 +1,15 @@
-use std::time::Instant;
+#[cfg(not(feature = "persistent-map"))]
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use std::cell::Cell;
 use std::cmp::Ordering;
 
+use crate::delta::wall_clock_micros;
+use crate::error::CacheError;
+use crate::hashing::KeyHashBuilder;
+use crate::transport::{self, Receiver, Sender};
+use crate::value::StoredValue;
+
 #[derive(Eq, PartialEq)]
 pub(crate) struct ExpirationEntry {
     pub expiration: Instant,
@@ -19,9 +28,229 @@ impl PartialOrd for ExpirationEntry {
     }
 }
 
+#[cfg(not(feature = "persistent-map"))]
+pub(crate) type EntryMap = HashMap<String, CacheEntry, KeyHashBuilder>;
+
+// Structurally shared map: cloning it for a snapshot is O(1), and later writes
+// copy only the nodes they touch.
+#[cfg(feature = "persistent-map")]
+pub(crate) type EntryMap = imbl::GenericHashMap<String, CacheEntry, KeyHashBuilder, imbl::shared_ptr::DefaultSharedPtr>;
+
+#[derive(Clone)]
 pub(crate) struct CacheEntry {
-    pub value: String,
+    pub value: StoredValue,
     pub expiration: Option<Instant>,
+    pub inserted_at: Instant,
     pub last_accessed: Instant,
     pub access_count: u64,
-}
\ No newline at end of file
+    // Wall-clock micros of the last value write, for last-writer-wins merges
+    pub written_at: u64,
+    pub checksum: Option<u32>,
+}
+
+impl CacheEntry {
+    // In-place value updates go through here so the checksum, when enabled,
+    // stays in step with the value
+    pub fn set_value(&mut self, value: StoredValue) {
+        self.checksum = self.checksum.map(|_| value.checksum());
+        self.value = value;
+        self.written_at = wall_clock_micros();
+    }
+
+    pub fn is_intact(&self) -> bool {
+        self.checksum.is_none_or(|checksum| checksum == self.value.checksum())
+    }
+}
+
+// Result of Cache::lookup: unlike get, it tells a key nobody has written apart
+// from one recorded as missing upstream with set_negative
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup {
+    Hit(String),
+    NotFoundCached,
+    Miss,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    After(Duration),
+    Persist,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SetOptions {
+    pub(crate) ttl: Option<Duration>,
+    pub(crate) keep_ttl: Option<bool>,
+}
+
+impl SetOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    // An explicit TTL still wins; this only applies when no TTL is given
+    pub fn with_keep_ttl(mut self, keep_ttl: bool) -> Self {
+        self.keep_ttl = Some(keep_ttl);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryOrder {
+    Insertion,
+    LastAccess,
+    Expiration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    pub(crate) pattern: Option<String>,
+    pub(crate) count: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            pattern: None,
+            count: 10,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    // Number of keys examined per call, as with Redis' COUNT hint
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count.max(1);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanPage {
+    pub(crate) keys: Vec<String>,
+    pub(crate) cursor: Option<String>,
+}
+
+impl ScanPage {
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    // Pass this back to continue the scan; None once every key has been visited
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    pub fn into_keys(self) -> Vec<String> {
+        self.keys
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub(crate) idle_time: Duration,
+    pub(crate) frequency: u64,
+    pub(crate) age: Duration,
+    pub(crate) ttl: Option<Duration>,
+}
+
+impl ObjectInfo {
+    pub fn idle_time(&self) -> Duration {
+        self.idle_time
+    }
+
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Clamp,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CounterBounds {
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    pub(crate) overflow: Overflow,
+}
+
+impl CounterBounds {
+    pub fn new(min: f64, max: f64) -> Self {
+        Self {
+            min: min.min(max),
+            max: max.max(min),
+            overflow: Overflow::Clamp,
+        }
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+// A response channel owned by the caller and reused across calls, so hot paths
+// don't allocate a fresh channel per operation. Keep one per thread: callers
+// sharing a slot would receive each other's responses.
+//
+// While a call is in flight the slot's only sender travels with the command
+// and comes back with the reply. A command dropped unanswered therefore
+// disconnects the channel rather than leaving the caller waiting, and the
+// next call starts over on a fresh one.
+pub struct ResponseSlot<T> {
+    channel: Cell<Option<SlotChannel<T>>>,
+}
+
+type SlotChannel<T> = (Sender<SlotReply<T>>, Receiver<SlotReply<T>>);
+
+pub(crate) struct SlotReply<T> {
+    pub result: Result<T, CacheError>,
+    pub sender: Sender<SlotReply<T>>,
+}
+
+impl<T> ResponseSlot<T> {
+    pub fn new() -> Self {
+        Self { channel: Cell::new(Some(transport::response_channel())) }
+    }
+
+    pub(crate) fn take(&self) -> SlotChannel<T> {
+        self.channel.take().unwrap_or_else(transport::response_channel)
+    }
+
+    pub(crate) fn wait(&self, receiver: Receiver<SlotReply<T>>) -> Result<T, CacheError> {
+        let reply = receiver.recv().map_err(|_| CacheError::LockError)?;
+        self.channel.set(Some((reply.sender, receiver)));
+        reply.result
+    }
+}
+
+impl<T> Default for ResponseSlot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
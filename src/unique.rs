@@ -0,0 +1,151 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+// 2^14 one-byte registers: 16 KiB per bucket, about 0.8% standard error, the
+// same trade-off as Redis' PFCOUNT
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+
+// Approximate count of distinct items in fixed memory. Each item's hash picks
+// a register by its low bits, which keeps the longest run of trailing zeros
+// (plus one) seen in the remaining bits; registers merge by taking the
+// maximum. Which end the run is counted from doesn't matter for a uniform
+// hash.
+#[derive(Debug, Clone)]
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog { registers: vec![0; REGISTERS] }
+    }
+
+    fn add(&mut self, item: &str) {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash as usize) & (REGISTERS - 1);
+        // Ones above the used bits cap the run at 64 - PRECISION
+        let rank = ((hash >> PRECISION) | (1 << (64 - PRECISION))).trailing_zeros() as u8 + 1;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    fn merge(&mut self, other: &HyperLogLog) {
+        for (ours, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *ours = (*ours).max(*theirs);
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&rank| 2f64.powi(-(rank as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        // Small cardinalities: count empty registers instead (linear counting)
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if raw <= 2.5 * m && empty > 0 {
+            return (m * (m / empty as f64).ln()).round() as u64;
+        }
+        raw.round() as u64
+    }
+}
+
+// One HyperLogLog per time bucket, so a count over the last N buckets is the
+// merge of just those. Buckets older than the retention window are dropped.
+#[derive(Debug)]
+pub(crate) struct UniqueCounter {
+    origin: Instant,
+    bucket: Duration,
+    retention: Duration,
+    // (bucket number since origin, sketch), oldest first
+    buckets: VecDeque<(u64, HyperLogLog)>,
+}
+
+impl UniqueCounter {
+    pub fn new(bucket: Duration, retention: Duration, now: Instant) -> Self {
+        UniqueCounter { origin: now, bucket, retention, buckets: VecDeque::new() }
+    }
+
+    pub fn add(&mut self, item: &str, now: Instant) {
+        let current = self.bucket_number(now);
+        if self.buckets.back().is_none_or(|(number, _)| *number != current) {
+            self.buckets.push_back((current, HyperLogLog::new()));
+        }
+        if let Some((_, sketch)) = self.buckets.back_mut() {
+            sketch.add(item);
+        }
+        self.prune(now);
+    }
+
+    // Distinct items over the buckets covering the last `window`, the current
+    // one included; the window is capped at the retention
+    pub fn count(&self, window: Duration, now: Instant) -> u64 {
+        let first = self.bucket_number(now).saturating_sub(self.buckets_in(window.min(self.retention)) - 1);
+        let mut merged = HyperLogLog::new();
+        for (_, sketch) in self.buckets.iter().filter(|(number, _)| *number >= first) {
+            merged.merge(sketch);
+        }
+        merged.estimate()
+    }
+
+    pub fn prune(&mut self, now: Instant) {
+        let first = self.bucket_number(now).saturating_sub(self.buckets_in(self.retention) - 1);
+        while self.buckets.front().is_some_and(|(number, _)| *number < first) {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn bucket_number(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.origin).as_nanos() / self.bucket.as_nanos()) as u64
+    }
+
+    fn buckets_in(&self, window: Duration) -> u64 {
+        (window.as_nanos().div_ceil(self.bucket.as_nanos()) as u64).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1.04 / sqrt(2^14) is about 0.81%; allow three standard errors
+    const TOLERANCE: f64 = 3.0 * 1.04 / 128.0;
+
+    fn assert_close(estimate: u64, actual: u64) {
+        let error = (estimate as f64 - actual as f64).abs() / actual as f64;
+        assert!(error <= TOLERANCE, "estimated {} for {} distinct items ({:.2}% off)", estimate, actual, error * 100.0);
+    }
+
+    #[test]
+    fn estimate_is_within_the_standard_error() {
+        let mut sketch = HyperLogLog::new();
+        for i in 0..10_000 {
+            sketch.add(&format!("user:{}", i));
+        }
+        assert_close(sketch.estimate(), 10_000);
+
+        // Repeats don't count
+        for i in 0..10_000 {
+            sketch.add(&format!("user:{}", i));
+        }
+        assert_close(sketch.estimate(), 10_000);
+    }
+
+    #[test]
+    fn merged_sketches_count_the_union() {
+        let (mut left, mut right) = (HyperLogLog::new(), HyperLogLog::new());
+        for i in 0..6_000 {
+            left.add(&format!("user:{}", i));
+        }
+        for i in 4_000..10_000 {
+            right.add(&format!("user:{}", i));
+        }
+        left.merge(&right);
+        assert_close(left.estimate(), 10_000);
+        assert_eq!(HyperLogLog::new().estimate(), 0);
+    }
+}
@@ -0,0 +1,79 @@
+use std::hash::{DefaultHasher, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+// On 64-bit targets the tag, the length byte and 22 bytes fill 24 bytes, the
+// size of a String; the Arc<str> variant is a 16-byte fat pointer next to the tag
+pub(crate) const INLINE_CAPACITY: usize = 22;
+
+// Values short enough (IDs, flags, counters) are stored inline with no heap allocation
+#[derive(Clone)]
+pub(crate) enum StoredValue {
+    Inline { len: u8, bytes: [u8; INLINE_CAPACITY] },
+    Shared(Arc<str>),
+}
+
+// Pointers are half as wide on 32-bit targets, so there the enum is bigger than a String
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(std::mem::size_of::<StoredValue>() == std::mem::size_of::<String>());
+
+impl StoredValue {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            // Only ever built from a complete &str, so the prefix is valid UTF-8
+            StoredValue::Inline { len, bytes } => std::str::from_utf8(&bytes[..*len as usize])
+                .expect("inline value holds valid UTF-8"),
+            StoredValue::Shared(value) => value,
+        }
+    }
+
+    // SipHash with fixed keys, truncated: plenty to catch flipped bits
+    pub(crate) fn checksum(&self) -> u32 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(self.as_bytes());
+        hasher.finish() as u32
+    }
+
+    // Whether both are handles on the same shared allocation
+    pub(crate) fn is_same(&self, other: &StoredValue) -> bool {
+        match (self, other) {
+            (StoredValue::Shared(ours), StoredValue::Shared(theirs)) => Arc::ptr_eq(ours, theirs),
+            _ => false,
+        }
+    }
+
+    fn inline(value: &str) -> Option<Self> {
+        if value.len() > INLINE_CAPACITY {
+            return None;
+        }
+        let mut bytes = [0u8; INLINE_CAPACITY];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        Some(StoredValue::Inline { len: value.len() as u8, bytes })
+    }
+}
+
+impl Deref for StoredValue {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<String> for StoredValue {
+    fn from(value: String) -> Self {
+        StoredValue::inline(&value).unwrap_or_else(|| StoredValue::Shared(value.into()))
+    }
+}
+
+impl From<&str> for StoredValue {
+    fn from(value: &str) -> Self {
+        StoredValue::inline(value).unwrap_or_else(|| StoredValue::Shared(value.into()))
+    }
+}
+
+impl From<Arc<str>> for StoredValue {
+    fn from(value: Arc<str>) -> Self {
+        StoredValue::inline(&value).unwrap_or(StoredValue::Shared(value))
+    }
+}
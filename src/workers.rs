@@ -0,0 +1,47 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// Threads the event loop hands CPU-heavy value work to, so a large value
+// doesn't hold up every other command. Jobs must not need the loop's state;
+// anything that has to change it goes back as a command.
+//
+// The workers are detached rather than joined: once the pool is dropped, each
+// finishes the jobs already handed out and exits when the queue is empty.
+// Joining could deadlock, since a job's callback may drop the last Cache and
+// so be the one waiting for the event loop, and with it this pool, to finish.
+pub(crate) struct WorkerPool {
+    sender: Sender<Job>,
+}
+
+impl WorkerPool {
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..threads {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || run_worker(&receiver));
+        }
+        WorkerPool { sender }
+    }
+
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.sender.send(Box::new(job));
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released before the job runs
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+// Cache::new repairs invalid settings instead of failing, and says so in its
+// stats.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, CacheError};
+
+#[test]
+fn repaired_settings_are_reported_in_stats() {
+    let invalid = || CacheConfig::new()
+        .with_cleanup_interval(Duration::ZERO)
+        .with_default_ttl(Duration::ZERO);
+    assert!(matches!(Cache::try_new(invalid()), Err(CacheError::ConfigError(_))));
+
+    let cache = Cache::new(invalid());
+    cache.set("key".to_string(), "value".to_string(), None).unwrap();
+    // Without the repair a zero default TTL would have expired it already
+    assert_eq!(cache.get("key").unwrap().as_deref(), Some("value"));
+    let stats = cache.get_stats().unwrap();
+    assert_eq!(stats.repaired_settings(), ["cleanup_interval", "default_ttl"]);
+
+    let valid = Cache::new(CacheConfig::new());
+    assert!(valid.get_stats().unwrap().repaired_settings().is_empty());
+}
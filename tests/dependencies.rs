@@ -0,0 +1,22 @@
+// A plain write replaces a derived entry, dropping the dependencies it was
+// stored with.
+
+use mini_redis::{Cache, CacheConfig};
+
+#[test]
+fn overwritten_entry_survives_its_old_dependency() {
+    let cache = Cache::new(CacheConfig::new());
+    cache.set("user:1".to_string(), "alice".to_string(), None).unwrap();
+    cache.set("page:users".to_string(), "<alice>".to_string(), None).unwrap();
+    cache.set_with_dependencies("summary".to_string(), "derived".to_string(), None, vec!["user:1".to_string(), "page:users".to_string()]).unwrap();
+
+    cache.set("summary".to_string(), "written directly".to_string(), None).unwrap();
+    cache.delete("user:1").unwrap();
+    assert_eq!(cache.get("summary").unwrap(), Some("written directly".to_string()));
+
+    // The same through a bulk write
+    cache.set_with_dependencies("summary".to_string(), "derived".to_string(), None, vec!["page:users".to_string()]).unwrap();
+    cache.bulk_set(vec![("summary".to_string(), "bulk".to_string())]).unwrap();
+    cache.set("page:users".to_string(), "<bob>".to_string(), None).unwrap();
+    assert_eq!(cache.get("summary").unwrap(), Some("bulk".to_string()));
+}
@@ -0,0 +1,37 @@
+// Keys past their TTL give up their max_size slot before any live entry is
+// evicted, even when cleanup hasn't run since they expired.
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, ManualClock};
+
+#[test]
+fn expired_keys_make_room_before_live_ones_are_evicted() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(
+        CacheConfig::new()
+            .with_clock(clock.clone())
+            .with_max_size(NonZeroUsize::new(2).unwrap())
+            .with_cleanup_interval(Duration::from_secs(3600)),
+    );
+    // The least recently used entry is the live one
+    cache.set("live".to_string(), "v".to_string(), None).unwrap();
+    clock.advance(Duration::from_millis(1));
+    cache.set("short".to_string(), "v".to_string(), Some(Duration::from_millis(10))).unwrap();
+    clock.advance(Duration::from_millis(20));
+
+    cache.set("new".to_string(), "v".to_string(), None).unwrap();
+    assert_eq!(cache.get("live").unwrap(), Some("v".to_string()));
+    assert_eq!(cache.get("new").unwrap(), Some("v".to_string()));
+    assert_eq!(cache.len().unwrap(), 2);
+
+    // The same for batches, trimmed in one pass
+    cache.delete("new").unwrap();
+    clock.advance(Duration::from_millis(1));
+    cache.set("short".to_string(), "v".to_string(), Some(Duration::from_millis(10))).unwrap();
+    clock.advance(Duration::from_millis(20));
+    cache.bulk_set(vec![("bulk".to_string(), "v".to_string())]).unwrap();
+    assert_eq!(cache.get("live").unwrap(), Some("v".to_string()));
+    assert_eq!(cache.get("bulk").unwrap(), Some("v".to_string()));
+}
@@ -0,0 +1,40 @@
+// Writes that fail, or write nothing, leave leases, cached misses and
+// dependent entries alone.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, Lookup};
+
+#[test]
+fn bulk_set_nx_that_writes_nothing_keeps_marks() {
+    let cache = Cache::new(CacheConfig::new());
+    cache.set("taken".to_string(), "v".to_string(), None).unwrap();
+    cache.set_negative("missing".to_string(), None).unwrap();
+    assert!(cache.try_lease("missing", Duration::from_secs(60)).unwrap());
+
+    let items = vec![("taken".to_string(), "new".to_string()), ("missing".to_string(), "new".to_string())];
+    assert!(!cache.bulk_set_nx(items).unwrap());
+
+    assert!(matches!(cache.lookup("missing").unwrap(), Lookup::NotFoundCached));
+    assert!(!cache.try_lease("missing", Duration::from_secs(60)).unwrap());
+}
+
+#[test]
+fn failed_increment_keeps_leases_and_dependents() {
+    let cache = Cache::new(CacheConfig::new());
+    cache.set("counter".to_string(), "not a number".to_string(), None).unwrap();
+    cache.set_with_dependencies("report".to_string(), "derived".to_string(), None, vec!["counter".to_string()]).unwrap();
+    assert!(cache.try_lease("counter", Duration::from_secs(60)).unwrap());
+
+    assert!(cache.incr_by_float("counter", 1.0).is_err());
+    assert!(!cache.try_lease("counter", Duration::from_secs(60)).unwrap());
+    assert_eq!(cache.get("report").unwrap().as_deref(), Some("derived"));
+
+    // An increment that lands still settles both
+    cache.set("counter".to_string(), "1".to_string(), None).unwrap();
+    cache.set_with_dependencies("report".to_string(), "derived".to_string(), None, vec!["counter".to_string()]).unwrap();
+    assert!(cache.try_lease("counter", Duration::from_secs(60)).unwrap());
+    assert_eq!(cache.incr_by_float("counter", 1.0).unwrap(), 2.0);
+    assert!(cache.try_lease("counter", Duration::from_secs(60)).unwrap());
+    assert_eq!(cache.get("report").unwrap(), None);
+}
@@ -0,0 +1,19 @@
+// FaultInjectingCache rules firing the scripted number of times.
+
+use mini_redis::{Cache, CacheConfig, CacheError, Failure, FaultInjectingCache, FaultRule};
+
+#[test]
+fn rule_limited_to_zero_times_never_fires() {
+    let cache = FaultInjectingCache::new(Cache::new(CacheConfig::new()));
+    cache.cache().set("user:1".to_string(), "v1".to_string(), None).unwrap();
+
+    cache.inject(FaultRule::new(Failure::Timeout).on_key("user:1").times(0));
+    assert_eq!(cache.get("user:1").unwrap(), Some("v1".to_string()));
+    assert_eq!(cache.injected("get"), 0);
+
+    // Later rules still work
+    cache.inject(FaultRule::new(Failure::Overloaded).on_key("user:1").times(1));
+    assert!(matches!(cache.get("user:1"), Err(CacheError::Overloaded)));
+    assert_eq!(cache.get("user:1").unwrap(), Some("v1".to_string()));
+    assert_eq!(cache.injected("get"), 1);
+}
@@ -0,0 +1,54 @@
+// Leaderboard ranking, neighbourhoods and periodic resets.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, CacheError, Leaderboard, ManualClock};
+
+#[test]
+fn ranks_members_by_score() {
+    let cache = Cache::new(CacheConfig::new());
+    let board = Leaderboard::open(&cache, "game", None).unwrap();
+    for (member, score) in [("ann", 30.0), ("bob", 10.0), ("cat", 20.0), ("dan", 40.0), ("eve", 20.0)] {
+        board.add_score(member, score).unwrap();
+    }
+    assert_eq!(board.add_score("bob", 25.0).unwrap(), 35.0);
+
+    let top: Vec<_> = board.top(3).unwrap().into_iter().map(|(member, _)| member).collect();
+    assert_eq!(top, ["dan", "bob", "ann"]);
+    assert_eq!(board.rank("dan").unwrap(), Some(0));
+    // Equal scores rank by name
+    assert_eq!(board.rank("cat").unwrap(), Some(3));
+    assert_eq!(board.rank("eve").unwrap(), Some(4));
+    assert_eq!(board.rank("zed").unwrap(), None);
+
+    let around: Vec<_> = board.around("ann", 1).unwrap().into_iter().map(|(member, _)| member).collect();
+    assert_eq!(around, ["bob", "ann", "cat"]);
+    let around: Vec<_> = board.around("dan", 2).unwrap().into_iter().map(|(member, _)| member).collect();
+    assert_eq!(around, ["dan", "bob", "ann"]);
+    assert!(board.around("zed", 2).unwrap().is_empty());
+
+    assert!(matches!(board.add_score("ann", f64::NAN), Err(CacheError::ValueNotNumeric(_))));
+    // Reopening keeps the scores
+    let again = Leaderboard::open(&cache, "game", None).unwrap();
+    assert_eq!(again.rank("dan").unwrap(), Some(0));
+
+    cache.flush().unwrap();
+    assert!(matches!(board.top(1), Err(CacheError::KeyNotFound)));
+}
+
+#[test]
+fn resets_once_per_period() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new().with_clock(clock.clone()));
+    let daily = Leaderboard::open(&cache, "daily", Some(Duration::from_secs(86_400))).unwrap();
+    daily.add_score("ann", 5.0).unwrap();
+
+    clock.advance(Duration::from_secs(86_399));
+    assert_eq!(daily.top(10).unwrap(), vec![("ann".to_string(), 5.0)]);
+
+    clock.advance(Duration::from_secs(1));
+    assert!(daily.top(10).unwrap().is_empty());
+    assert_eq!(daily.add_score("ann", 1.0).unwrap(), 1.0);
+
+    assert!(Leaderboard::open(&cache, "broken", Some(Duration::ZERO)).is_err());
+}
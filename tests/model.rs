@@ -0,0 +1,176 @@
+// Runs random command sequences against the cache and against a plain
+// HashMap model on the same ManualClock, and checks every reply matches.
+// A failure prints the seed and the commands that led up to it.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, ManualClock};
+
+const SEQUENCES: u64 = 200;
+const COMMANDS_PER_SEQUENCE: usize = 500;
+const KEYS: u64 = 8;
+const VALUES: u64 = 4;
+
+// xorshift64*, good enough for picking commands
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn key(&mut self) -> String {
+        format!("key{}", self.below(KEYS))
+    }
+
+    fn value(&mut self) -> String {
+        format!("value{}", self.below(VALUES))
+    }
+
+    fn ttl(&mut self) -> Duration {
+        Duration::from_millis(1 + self.below(100))
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Set(String, String, Option<Duration>),
+    Get(String),
+    Delete(String),
+    UpdateTtl(String, Duration),
+    CompareAndSwap(String, String, String),
+    BulkGet(Vec<String>),
+    Flush,
+    Advance(Duration),
+}
+
+impl Command {
+    fn random(rng: &mut Rng) -> Command {
+        match rng.below(100) {
+            0..=24 => Command::Set(rng.key(), rng.value(), if rng.below(2) == 0 { Some(rng.ttl()) } else { None }),
+            25..=49 => Command::Get(rng.key()),
+            50..=57 => Command::Delete(rng.key()),
+            58..=65 => Command::UpdateTtl(rng.key(), rng.ttl()),
+            66..=75 => Command::CompareAndSwap(rng.key(), rng.value(), rng.value()),
+            76..=83 => Command::BulkGet((0..1 + rng.below(4)).map(|_| rng.key()).collect()),
+            84 => Command::Flush,
+            _ => Command::Advance(Duration::from_millis(rng.below(40))),
+        }
+    }
+}
+
+// Entries hold their deadline as time since the clock started
+#[derive(Default)]
+struct Model {
+    now: Duration,
+    entries: HashMap<String, (String, Option<Duration>)>,
+}
+
+impl Model {
+    fn live(&mut self, key: &str) -> Option<&mut (String, Option<Duration>)> {
+        let now = self.now;
+        if self.entries.get(key).is_some_and(|(_, deadline)| deadline.is_some_and(|deadline| deadline <= now)) {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    fn get(&mut self, key: &str) -> Option<String> {
+        self.live(key).map(|(value, _)| value.clone())
+    }
+
+    // The cache and the model disagree, by design, only at the exact instant
+    // an entry expires, so the clock is never left there
+    fn advance(&mut self, by: Duration) -> Duration {
+        let mut target = self.now + by;
+        while self.entries.values().any(|(_, deadline)| *deadline == Some(target)) {
+            target += Duration::from_millis(1);
+        }
+        let step = target - self.now;
+        self.now = target;
+        step
+    }
+}
+
+fn run_sequence(seed: u64) {
+    let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new().with_clock(clock.clone()));
+    let mut model = Model::default();
+    let mut history = Vec::new();
+
+    for _ in 0..COMMANDS_PER_SEQUENCE {
+        let command = Command::random(&mut rng);
+        let (actual, expected) = match &command {
+            Command::Set(key, value, ttl) => {
+                cache.set(key.clone(), value.clone(), *ttl).unwrap();
+                model.entries.insert(key.clone(), (value.clone(), ttl.map(|ttl| model.now + ttl)));
+                (String::new(), String::new())
+            },
+            Command::Get(key) => (format!("{:?}", cache.get(key).unwrap()), format!("{:?}", model.get(key))),
+            Command::Delete(key) => {
+                let expected = model.live(key).is_some();
+                model.entries.remove(key);
+                (format!("{:?}", cache.delete(key).unwrap()), format!("{:?}", expected))
+            },
+            Command::UpdateTtl(key, ttl) => {
+                let deadline = model.now + *ttl;
+                let expected = model.live(key).map(|entry| entry.1 = Some(deadline)).is_some();
+                (format!("{:?}", cache.update_ttl(key, *ttl).unwrap()), format!("{:?}", expected))
+            },
+            Command::CompareAndSwap(key, expected_value, new_value) => {
+                let expected = match model.live(key) {
+                    Some(entry) if entry.0 == *expected_value => {
+                        entry.0 = new_value.clone();
+                        true
+                    },
+                    _ => false,
+                };
+                let actual = cache.compare_and_swap(key, expected_value, new_value.clone()).unwrap();
+                (format!("{:?}", actual), format!("{:?}", expected))
+            },
+            Command::BulkGet(keys) => {
+                let actual = cache.bulk_get(keys.iter().map(String::as_str)).unwrap();
+                let mut actual: Vec<_> = actual.into_iter().map(|(key, value)| (key.to_string(), value)).collect();
+                actual.sort();
+                let mut expected: Vec<_> = keys.iter().map(|key| (key.clone(), model.get(key))).collect();
+                expected.sort();
+                expected.dedup();
+                (format!("{:?}", actual), format!("{:?}", expected))
+            },
+            Command::Flush => {
+                cache.flush().unwrap();
+                model.entries.clear();
+                (String::new(), String::new())
+            },
+            Command::Advance(by) => {
+                clock.advance(model.advance(*by));
+                (String::new(), String::new())
+            },
+        };
+        history.push(command);
+
+        if actual != expected {
+            let history: Vec<String> = history.iter().map(|command| format!("{:?}", command)).collect();
+            panic!(
+                "seed {} diverged at command {}: cache returned {}, model {}\nhistory:\n{}",
+                seed, history.len(), actual, expected, history.join("\n")
+            );
+        }
+    }
+}
+
+#[test]
+fn matches_model() {
+    for seed in 0..SEQUENCES {
+        run_sequence(seed);
+    }
+}
@@ -0,0 +1,27 @@
+// Keys rewritten by the key normalizer are counted against their canonical
+// key's prefix.
+
+use mini_redis::{Cache, CacheConfig};
+
+#[test]
+fn normalized_keys_are_attributed_to_the_canonical_prefix() {
+    let cache = Cache::new(
+        CacheConfig::new()
+            .with_key_normalizer(|key| key.split('?').next().unwrap_or(key).to_lowercase())
+            .with_tracked_prefixes(["html:", "user:"]),
+    );
+
+    cache.set("html:/Home".to_string(), "<p>".to_string(), None).unwrap();
+    assert_eq!(cache.get("html:/home?utm_source=mail").unwrap().as_deref(), Some("<p>"));
+    assert_eq!(cache.get("HTML:/home?ref=ad").unwrap().as_deref(), Some("<p>"));
+    assert_eq!(cache.get("html:/home").unwrap().as_deref(), Some("<p>"));
+    assert_eq!(cache.get("user:1").unwrap(), None);
+
+    let stats = cache.get_stats().unwrap();
+    assert_eq!(stats.normalized_keys(), 3);
+    assert_eq!(stats.hits(), 3);
+    let html = stats.prefix_stats().iter().find(|prefix| prefix.prefix() == "html:").unwrap();
+    assert_eq!((html.hits(), html.misses(), html.normalized_keys()), (3, 0, 3));
+    let user = stats.prefix_stats().iter().find(|prefix| prefix.prefix() == "user:").unwrap();
+    assert_eq!((user.hits(), user.misses(), user.normalized_keys()), (0, 1, 0));
+}
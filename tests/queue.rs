@@ -0,0 +1,104 @@
+// Work queue deliveries, acks and redelivery after the visibility timeout.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, ManualClock, MessageQueue};
+
+#[test]
+fn unacked_messages_come_back_after_the_visibility_timeout() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new().with_clock(clock.clone()));
+    let jobs = MessageQueue::new(&cache, "jobs");
+    jobs.enqueue("resize:1".to_string()).unwrap();
+    jobs.enqueue("resize:2".to_string()).unwrap();
+
+    let visibility = Duration::from_secs(30);
+    let (first, body) = jobs.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "resize:1");
+    let (second, body) = jobs.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "resize:2");
+    assert_eq!(jobs.dequeue_with_visibility(visibility).unwrap(), None);
+    assert_eq!(jobs.len().unwrap(), (0, 2));
+
+    assert!(jobs.ack(second).unwrap());
+    assert!(!jobs.ack(second).unwrap());
+
+    // The first consumer never acks
+    clock.advance(Duration::from_secs(31));
+    assert_eq!(jobs.len().unwrap(), (1, 0));
+    let (redelivered, body) = jobs.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "resize:1");
+    // A late ack under the old receipt doesn't settle the new delivery
+    assert!(!jobs.ack(first).unwrap());
+    assert!(jobs.ack(redelivered).unwrap());
+    assert_eq!(jobs.len().unwrap(), (0, 0));
+
+    assert!(jobs.dequeue_with_visibility(Duration::ZERO).is_err());
+    assert_eq!(MessageQueue::new(&cache, "missing").dequeue_with_visibility(visibility).unwrap(), None);
+}
+
+#[test]
+fn receipts_stay_unique_when_a_queue_is_dropped_and_recreated() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new()
+        .with_clock(clock.clone())
+        .with_cleanup_interval(Duration::from_secs(1)));
+    let jobs = MessageQueue::new(&cache, "jobs");
+    let visibility = Duration::from_secs(30);
+
+    jobs.enqueue("first".to_string()).unwrap();
+    let (stale, _) = jobs.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert!(jobs.ack(stale).unwrap());
+
+    // The empty queue is dropped by the next cleanup, which runs after the
+    // command that wakes the loop
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(jobs.len().unwrap(), (0, 0));
+
+    jobs.enqueue("second".to_string()).unwrap();
+    let (receipt, body) = jobs.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "second");
+    assert_ne!(receipt, stale);
+    // A late ack under the old receipt doesn't settle the new delivery
+    assert!(!jobs.ack(stale).unwrap());
+    assert_eq!(jobs.len().unwrap(), (0, 1));
+
+    // Nor does flushing start the receipts over
+    cache.flush().unwrap();
+    jobs.enqueue("third".to_string()).unwrap();
+    let (after_flush, _) = jobs.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert!(after_flush != stale && after_flush != receipt);
+}
+
+#[test]
+fn delayed_messages_stay_hidden_until_due() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new()
+        .with_clock(clock.clone())
+        .with_cleanup_interval(Duration::from_secs(1)));
+    let retries = MessageQueue::new(&cache, "retries");
+    let visibility = Duration::from_secs(30);
+
+    retries.enqueue_delayed("later".to_string(), Duration::from_secs(60)).unwrap();
+    retries.enqueue_delayed("sooner".to_string(), Duration::from_secs(10)).unwrap();
+    retries.enqueue("now".to_string()).unwrap();
+    assert_eq!(retries.len().unwrap(), (1, 0));
+
+    let (receipt, body) = retries.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "now");
+    assert!(retries.ack(receipt).unwrap());
+    assert_eq!(retries.dequeue_with_visibility(visibility).unwrap(), None);
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(retries.len().unwrap(), (1, 0));
+    let (receipt, body) = retries.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "sooner");
+    assert!(retries.ack(receipt).unwrap());
+
+    // Only delayed messages left: cleanup keeps the queue
+    clock.advance(Duration::from_secs(49));
+    assert_eq!(retries.dequeue_with_visibility(visibility).unwrap(), None);
+    clock.advance(Duration::from_secs(1));
+    let (_, body) = retries.dequeue_with_visibility(visibility).unwrap().unwrap();
+    assert_eq!(body, "later");
+}
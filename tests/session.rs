@@ -0,0 +1,74 @@
+// Session tokens, sliding expiry and the per-user session index.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, ManualClock, SessionStore};
+
+#[test]
+fn sessions_slide_while_used_and_expire_when_idle() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new().with_clock(clock.clone()));
+    let sessions = SessionStore::new(&cache, "session", Duration::from_secs(60));
+
+    let token = sessions.create_session("alice", "cart=3".to_string()).unwrap();
+    assert_eq!(token.len(), 32);
+    assert_eq!(sessions.get_session(&token).unwrap().as_deref(), Some("cart=3"));
+
+    // Each read restarts the TTL, so the session outlives its first minute
+    clock.advance(Duration::from_secs(45));
+    assert!(sessions.touch_session(&token).unwrap());
+    clock.advance(Duration::from_secs(45));
+    assert_eq!(sessions.get_session(&token).unwrap().as_deref(), Some("cart=3"));
+    assert_eq!(sessions.sessions_for_user("alice").unwrap(), vec![token.clone()]);
+
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(sessions.get_session(&token).unwrap(), None);
+    assert!(!sessions.touch_session(&token).unwrap());
+    assert!(sessions.sessions_for_user("alice").unwrap().is_empty());
+}
+
+#[test]
+fn users_list_only_their_live_sessions() {
+    let cache = Cache::new(CacheConfig::new());
+    let sessions = SessionStore::new(&cache, "session", Duration::from_secs(60));
+
+    let laptop = sessions.create_session("alice", String::new()).unwrap();
+    let phone = sessions.create_session("alice", "theme=dark".to_string()).unwrap();
+    let other = sessions.create_session("bob", String::new()).unwrap();
+    assert_ne!(laptop, phone);
+    assert_eq!(sessions.sessions_for_user("alice").unwrap(), vec![laptop.clone(), phone.clone()]);
+    assert_eq!(sessions.sessions_for_user("bob").unwrap(), vec![other]);
+    assert!(sessions.sessions_for_user("carol").unwrap().is_empty());
+
+    assert!(sessions.destroy_session(&laptop).unwrap());
+    assert!(!sessions.destroy_session(&laptop).unwrap());
+    assert_eq!(sessions.get_session(&laptop).unwrap(), None);
+    assert_eq!(sessions.sessions_for_user("alice").unwrap(), vec![phone.clone()]);
+    assert_eq!(sessions.get_session(&phone).unwrap().as_deref(), Some("theme=dark"));
+
+    // User names containing the separators don't confuse the stored owner
+    let odd = sessions.create_session("a:b,c", "x:y".to_string()).unwrap();
+    assert_eq!(sessions.get_session(&odd).unwrap().as_deref(), Some("x:y"));
+    assert_eq!(sessions.sessions_for_user("a:b,c").unwrap(), vec![odd]);
+}
+
+#[test]
+fn concurrent_logins_all_land_in_the_index() {
+    let cache = Cache::new(CacheConfig::new());
+    let sessions = SessionStore::new(&cache, "session", Duration::from_secs(60));
+
+    // Far more contending updates than a bounded retry budget would survive
+    let mut tokens: Vec<String> = std::thread::scope(|scope| {
+        let logins: Vec<_> = (0..16)
+            .map(|_| scope.spawn(|| {
+                (0..25).map(|_| sessions.create_session("alice", String::new()).unwrap()).collect::<Vec<_>>()
+            }))
+            .collect();
+        logins.into_iter().flat_map(|login| login.join().unwrap()).collect()
+    });
+
+    let mut indexed = sessions.sessions_for_user("alice").unwrap();
+    tokens.sort();
+    indexed.sort();
+    assert_eq!(indexed, tokens);
+}
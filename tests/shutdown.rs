@@ -0,0 +1,67 @@
+// Commands still queued when the event loop stops are answered, not dropped.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use mini_redis::{CacheConfig, CacheError, CacheRegistry, ResponseSlot};
+
+#[test]
+fn queued_commands_fail_at_shutdown() {
+    let mut registry = CacheRegistry::new();
+    registry.insert("main", CacheConfig::new()).unwrap();
+    let cache = registry.get("main").unwrap().clone();
+
+    // Holds the event loop until the other commands are queued behind it,
+    // then shuts the registry down from the loop thread
+    let (started_sender, started_receiver) = mpsc::channel();
+    let (go_sender, go_receiver) = mpsc::channel();
+    cache
+        .set_with_callback("first".to_string(), "v".to_string(), None, move |_| {
+            started_sender.send(()).unwrap();
+            go_receiver.recv().unwrap();
+            registry.shutdown();
+        })
+        .unwrap();
+    started_receiver.recv().unwrap();
+
+    let (callback_sender, callback_receiver) = mpsc::channel();
+    cache
+        .get_with_callback("first", move |result| callback_sender.send(result).unwrap())
+        .unwrap();
+
+    let (slot_sender, slot_receiver) = mpsc::channel();
+    let handle = cache.clone();
+    thread::spawn(move || {
+        let slot = ResponseSlot::new();
+        slot_sender.send(handle.get_with_slot("first", &slot)).unwrap();
+    });
+    go_sender.send(()).unwrap();
+
+    let result = callback_receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("queued callback was dropped at shutdown");
+    assert!(matches!(result, Err(CacheError::LockError)));
+    let result = slot_receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("get_with_slot blocked after shutdown");
+    assert!(matches!(result, Err(CacheError::LockError)));
+}
+
+#[cfg(feature = "persistence")]
+#[test]
+fn registry_shutdown_waits_for_loops_with_clones_alive() {
+    let path = std::env::temp_dir().join(format!("mini-redis-registry-{}.snapshot", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut registry = CacheRegistry::new();
+    registry.insert("main", CacheConfig::new().with_persistence_path(&path)).unwrap();
+    let cache = registry.get("main").unwrap().clone();
+    cache.set("key".to_string(), "value".to_string(), None).unwrap();
+
+    registry.shutdown();
+    // Written by the loop on its way out, so it has finished
+    assert!(path.exists());
+    assert!(matches!(cache.get("key"), Err(CacheError::LockError)));
+    std::fs::remove_file(&path).unwrap();
+}
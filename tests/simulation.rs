@@ -0,0 +1,41 @@
+// Eviction simulations follow max_size and the simulation settings across
+// set_max_size and reload_config.
+
+use std::num::NonZeroUsize;
+
+use mini_redis::{Cache, CacheConfig};
+
+fn config(max_size: usize) -> CacheConfig {
+    CacheConfig::new().with_max_size(NonZeroUsize::new(max_size).unwrap())
+}
+
+// Policy simulation capacities, and the smallest and largest sizing curve
+// capacities
+fn capacities(cache: &Cache) -> (Vec<usize>, Option<usize>, Option<usize>) {
+    let stats = cache.get_stats().unwrap();
+    let curve: Vec<usize> = stats.sizing_curve().iter().map(|sim| sim.capacity()).collect();
+    (
+        stats.policy_simulations().iter().map(|sim| sim.capacity()).collect(),
+        curve.first().copied(),
+        curve.last().copied(),
+    )
+}
+
+#[test]
+fn simulations_are_rebuilt_for_new_settings() {
+    let cache = Cache::new(config(100).with_sizing_curve(true));
+    assert_eq!(capacities(&cache), (vec![], Some(100), Some(150)));
+
+    cache.set_max_size(NonZeroUsize::new(200)).unwrap();
+    assert_eq!(capacities(&cache), (vec![], Some(200), Some(300)));
+
+    // Rebuilt simulations start cold
+    cache.set("key".to_string(), "value".to_string(), None).unwrap();
+    cache.get("key").unwrap();
+    cache.reload_config(config(200).with_sizing_curve(true).with_policy_simulation(true)).unwrap();
+    assert_eq!(capacities(&cache), (vec![200, 200, 200], Some(200), Some(300)));
+    assert!(cache.get_stats().unwrap().sizing_curve().iter().all(|sim| sim.hit_rate() == 0.0));
+
+    cache.reload_config(config(50)).unwrap();
+    assert_eq!(capacities(&cache), (vec![], None, None));
+}
@@ -0,0 +1,21 @@
+// Time series counters reject buckets they can't key by millisecond.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, CacheError, TimeSeriesCounter};
+
+#[test]
+fn sub_millisecond_buckets_are_a_config_error() {
+    let cache = Cache::new(CacheConfig::new());
+    let day = Duration::from_secs(86_400);
+
+    for bucket in [Duration::ZERO, Duration::from_micros(999)] {
+        let result = TimeSeriesCounter::new(&cache, "views", bucket, day);
+        assert!(matches!(result, Err(CacheError::ConfigError(_))));
+    }
+
+    let views = TimeSeriesCounter::new(&cache, "views", Duration::from_secs(3600), day).unwrap();
+    views.incr(2.0).unwrap();
+    views.incr(1.5).unwrap();
+    assert_eq!(views.sum(day).unwrap(), 3.5);
+}
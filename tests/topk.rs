@@ -0,0 +1,35 @@
+// Top-k trackers through the cache.
+
+use mini_redis::{Cache, CacheConfig, CacheError};
+
+fn hits(items: &[(&str, usize)]) -> Vec<String> {
+    items.iter().flat_map(|(item, count)| std::iter::repeat_n(item.to_string(), *count)).collect()
+}
+
+#[test]
+fn list_holds_the_k_most_frequent_items_highest_first() {
+    let cache = Cache::new(CacheConfig::new());
+    assert!(matches!(cache.topk_list("endpoints"), Err(CacheError::KeyNotFound)));
+    assert!(matches!(cache.topk_reserve("endpoints", 0, 2048, 4), Err(CacheError::ConfigError(_))));
+
+    cache.topk_reserve("endpoints", 3, 2048, 4).unwrap();
+    assert!(cache.topk_list("endpoints").unwrap().is_empty());
+
+    cache.topk_add("endpoints", hits(&[("/cart", 1), ("/login", 3), ("/faq", 2)])).unwrap();
+    cache.topk_add("endpoints", hits(&[("/home", 5), ("/about", 3)])).unwrap();
+
+    // Equal counts are ordered by item
+    assert_eq!(cache.topk_list("endpoints").unwrap(), vec![
+        ("/home".to_string(), 5),
+        ("/about".to_string(), 3),
+        ("/login".to_string(), 3),
+    ]);
+
+    // An item overtaking the list's last entry takes its place
+    cache.topk_add("endpoints", hits(&[("/faq", 2)])).unwrap();
+    assert_eq!(cache.topk_list("endpoints").unwrap(), vec![
+        ("/home".to_string(), 5),
+        ("/faq".to_string(), 4),
+        ("/about".to_string(), 3),
+    ]);
+}
@@ -0,0 +1,38 @@
+// Unique counters through the cache, with windows sliding on a manual clock.
+
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig, CacheError, ManualClock};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn users(ids: &[u32]) -> Vec<String> {
+    ids.iter().map(|id| format!("user:{}", id)).collect()
+}
+
+#[test]
+fn counts_follow_the_window_as_it_slides() {
+    let clock = ManualClock::new();
+    let cache = Cache::new(CacheConfig::new().with_clock(clock.clone()));
+    assert!(matches!(cache.unique_add("visitors", users(&[1])), Err(CacheError::KeyNotFound)));
+
+    cache.unique_reserve("visitors", MINUTE, 10 * MINUTE).unwrap();
+    cache.unique_add("visitors", users(&[1, 2, 3, 1])).unwrap();
+    assert_eq!(cache.unique_count("visitors", MINUTE).unwrap(), 3);
+
+    // A second minute: two new users and one seen before
+    clock.advance(MINUTE);
+    cache.unique_add("visitors", users(&[3, 4, 5])).unwrap();
+    assert_eq!(cache.unique_count("visitors", MINUTE).unwrap(), 3);
+    assert_eq!(cache.unique_count("visitors", 2 * MINUTE).unwrap(), 5);
+
+    // The window slides past the first minute
+    clock.advance(MINUTE);
+    assert_eq!(cache.unique_count("visitors", MINUTE).unwrap(), 0);
+    assert_eq!(cache.unique_count("visitors", 2 * MINUTE).unwrap(), 3);
+    assert_eq!(cache.unique_count("visitors", 3 * MINUTE).unwrap(), 5);
+
+    // Buckets older than the retention are gone, however wide the window
+    clock.advance(10 * MINUTE);
+    assert_eq!(cache.unique_count("visitors", 60 * MINUTE).unwrap(), 0);
+}
@@ -0,0 +1,31 @@
+// Reads of large values finish on worker threads, and so do their callbacks.
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use mini_redis::{Cache, CacheConfig};
+
+#[test]
+fn last_clone_dropped_in_a_worker_callback() {
+    let cache = Cache::new(CacheConfig::new().with_value_workers(2));
+    cache.set("big".to_string(), "x".repeat(1 << 20), None).unwrap();
+
+    let handle = cache.clone();
+    let (go_sender, go_receiver) = mpsc::channel();
+    let (done_sender, done_receiver) = mpsc::channel();
+    handle
+        .get_with_callback("big", move |result| {
+            // Wait until this is the last clone, then drop it on the worker
+            go_receiver.recv().unwrap();
+            drop(cache);
+            done_sender.send(result.map(|value| value.map(|value| value.len()))).unwrap();
+        })
+        .unwrap();
+    drop(handle);
+    go_sender.send(()).unwrap();
+
+    let result = done_receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("dropping the last clone on a value worker deadlocked");
+    assert_eq!(result.unwrap(), Some(1 << 20));
+}